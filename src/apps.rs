@@ -1,15 +1,78 @@
-use std::{borrow::Cow, cmp::min, ops::Deref, process::Command, thread::sleep, time::Duration};
+use std::{
+    borrow::Cow,
+    cmp::min,
+    fs::File,
+    io::BufReader,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process::Command,
+    thread::sleep,
+    time::{Duration, UNIX_EPOCH},
+};
 
 use anyhow::Context as _;
-use applications::{AppInfo, AppInfoContext, common::SearchPath};
+use applications::{AppInfo, AppInfoContext, common::SearchPath, get_default_search_paths};
 use fork::daemon;
+use serde::{Deserialize, Serialize};
 use skim::prelude::*;
 
-use crate::SkimRun;
+use crate::{Mode, SkimRun, xdg};
+
+static INDEX_FILE: &str = "apps.json";
+
+/// Where a desktop entry was exported from
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AppSource {
+    #[default]
+    Native,
+    Flatpak {
+        user: bool,
+    },
+    Snap,
+}
+
+impl AppSource {
+    /// Guesses the source from the location of the desktop file
+    fn of(desktop_path: &Path) -> Self {
+        if desktop_path.starts_with(FLATPAK_SYSTEM_EXPORTS) {
+            Self::Flatpak { user: false }
+        } else if desktop_path.starts_with(flatpak_user_exports()) {
+            Self::Flatpak { user: true }
+        } else if desktop_path.starts_with(SNAP_EXPORTS) {
+            Self::Snap
+        } else {
+            Self::Native
+        }
+    }
+}
+
+impl std::fmt::Display for AppSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            AppSource::Native => "native",
+            AppSource::Flatpak { user: false } => "flatpak (system)",
+            AppSource::Flatpak { user: true } => "flatpak (user)",
+            AppSource::Snap => "snap",
+        };
+        write!(f, "{s}")
+    }
+}
+
+static FLATPAK_SYSTEM_EXPORTS: &str = "/var/lib/flatpak/exports/share/applications";
+static SNAP_EXPORTS: &str = "/var/lib/snapd/desktop/applications";
+
+fn flatpak_user_exports() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+        .unwrap_or_default()
+        .join("flatpak/exports/share/applications")
+}
 
 #[derive(Default)]
 pub struct App {
     app: applications::App,
+    source: AppSource,
 }
 impl Deref for App {
     type Target = applications::App;
@@ -20,32 +83,58 @@ impl Deref for App {
 }
 impl From<applications::App> for App {
     fn from(value: applications::App) -> Self {
-        Self { app: value }
+        Self {
+            source: AppSource::of(&value.app_desktop_path),
+            app: value,
+        }
+    }
+}
+
+impl App {
+    /// The application ID: the flatpak ref name, the snap name, or the desktop file name
+    #[must_use]
+    pub fn id(&self) -> &str {
+        let stem = self
+            .app
+            .app_desktop_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        match self.source {
+            // Snap exports are named `<snap>_<app>.desktop`
+            AppSource::Snap => stem.split('_').next().unwrap_or(stem),
+            _ => stem,
+        }
+    }
+    #[must_use]
+    pub fn source(&self) -> AppSource {
+        self.source
+    }
+    /// The command line to launch the app, split into program and arguments
+    fn command(&self) -> Option<Vec<String>> {
+        let exe = self.app_path_exe.as_ref()?.to_string_lossy();
+        shlex::split(&exe).filter(|args| !args.is_empty())
     }
 }
 
 impl SkimItem for App {
-    fn text(&self) -> Cow<str> {
-        if self.app.name.is_empty() {
-            Cow::Borrowed(
-                self.app
-                    .app_desktop_path
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .strip_suffix(".desktop")
-                    .unwrap(),
-            )
+    fn text(&self) -> Cow<'_, str> {
+        let name = if self.app.name.is_empty() {
+            self.id()
         } else {
-            Cow::Borrowed(&self.app.name)
+            &self.app.name
+        };
+        match self.source {
+            AppSource::Native => Cow::Borrowed(name),
+            AppSource::Flatpak { .. } => Cow::Owned(format!("[flatpak] {name}")),
+            AppSource::Snap => Cow::Owned(format!("[snap] {name}")),
         }
     }
     fn preview(&self, context: PreviewContext) -> ItemPreview {
         let width = u32::try_from(context.width).unwrap_or(16);
         let height = u32::try_from(context.height).unwrap_or(16);
         let size = min(16, min(height, width));
-        if self.icon_path.is_some() {
+        if let Some(icon_path) = &self.icon_path {
             let conf = viuer::Config {
                 width: Some(size),
                 x: 0,
@@ -58,34 +147,164 @@ impl SkimItem for App {
             };
             let img = image::DynamicImage::ImageRgb8(image::RgbImage::new(size, size));
             let _ = viuer::print(&img, &conf);
-            let _ = viuer::print_from_file(self.icon_path.clone().unwrap(), &conf);
+            let _ = viuer::print_from_file(icon_path, &conf);
         }
 
-        ItemPreview::Text(String::new())
+        // Half-block rendering uses one row per two pixels, keep the text below the icon
+        let padding = "\n".repeat(usize::try_from(size / 2 + 1).unwrap_or_default());
+        ItemPreview::Text(format!(
+            "{padding}ID: {}\nOrigin: {}",
+            self.id(),
+            self.source
+        ))
     }
-    fn output(&self) -> Cow<str> {
-        if self.app_path_exe.is_some() {
-            self.app_path_exe
-                .as_ref()
-                .unwrap()
-                .as_path()
-                .to_string_lossy()
-        } else {
-            Cow::default()
+    fn output(&self) -> Cow<'_, str> {
+        self.app_desktop_path.to_string_lossy()
+    }
+}
+
+/// On-disk index of the parsed desktop entries, with their icon paths already resolved.
+/// It is considered fresh as long as the modification times of the search paths and their
+/// entries are unchanged.
+#[derive(Default, Serialize, Deserialize)]
+struct AppIndex {
+    mtimes: Vec<(PathBuf, u64)>,
+    apps: Vec<applications::App>,
+}
+
+impl AppIndex {
+    fn path() -> PathBuf {
+        xdg::cache_dir().join(INDEX_FILE)
+    }
+    fn load() -> Option<Self> {
+        let file = File::open(Self::path()).ok()?;
+        serde_json::from_reader(BufReader::new(file)).ok()
+    }
+    fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).context("Failed to create cache dir")?;
         }
+        // Write to a temporary file first so a concurrent reader never sees a partial index
+        let tmp = path.with_extension("json.tmp");
+        serde_json::to_writer(File::create(&tmp)?, self).context("Failed to write app index")?;
+        std::fs::rename(&tmp, &path).context("Failed to replace app index")?;
+        Ok(())
+    }
+    /// Rescans every search path and builds a fresh index
+    fn build() -> anyhow::Result<Self> {
+        let mtimes = Apps::search_mtimes();
+        let mut ctx = AppInfoContext::new(Apps::search_paths());
+        ctx.refresh_apps()?;
+        Ok(Self {
+            mtimes,
+            apps: ctx.get_all_apps(),
+        })
+    }
+    fn is_fresh(&self) -> bool {
+        self.mtimes == Apps::search_mtimes()
     }
 }
 
 #[derive(Default, Clone, Copy)]
 pub struct Apps;
 
+impl Apps {
+    fn search_paths() -> Vec<SearchPath> {
+        vec![
+            SearchPath::new(PathBuf::from("/"), 1),
+            SearchPath::new(PathBuf::from(FLATPAK_SYSTEM_EXPORTS), 1),
+            SearchPath::new(flatpak_user_exports(), 1),
+            SearchPath::new(PathBuf::from(SNAP_EXPORTS), 1),
+        ]
+    }
+    /// Modification times of every directory searched for desktop entries, and of the
+    /// directories and desktop entries below them that are scanned at their search depth.
+    /// Adding, removing or renaming an entry bumps the mtime of its directory, editing it
+    /// in place bumps its own.
+    fn search_mtimes() -> Vec<(PathBuf, u64)> {
+        let mut dirs: Vec<SearchPath> = get_default_search_paths()
+            .into_iter()
+            .chain(Self::search_paths())
+            .collect();
+        // Keep the deepest search of each directory
+        dirs.sort_unstable_by(|a, b| a.path.cmp(&b.path).then(b.depth.cmp(&a.depth)));
+        dirs.dedup_by(|a, b| a.path == b.path);
+        let mut mtimes = Vec::new();
+        for dir in dirs {
+            collect_mtimes(&dir.path, dir.depth, &mut mtimes);
+        }
+        mtimes
+    }
+    fn refresh_index() -> anyhow::Result<AppIndex> {
+        let index = AppIndex::build()?;
+        index.save()?;
+        Ok(index)
+    }
+}
+
+/// The mtime of `path` in nanoseconds, 0 when unknown
+fn mtime(path: &Path) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+}
+
+/// Records the mtime of `path`, then of its desktop entries and, while
+/// their own entries are within `depth` too, of its subdirectories.
+/// Like `WalkDir::max_depth`, the entries of `path` are at depth 1: directories that are
+/// not scanned are left out, their mtimes change for unrelated reasons (`/tmp`, `/run`).
+fn collect_mtimes(path: &Path, depth: u8, mtimes: &mut Vec<(PathBuf, u64)>) {
+    mtimes.push((path.to_path_buf(), mtime(path)));
+    if depth == 0 {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    let mut children: Vec<(PathBuf, bool)> = entries
+        .flatten()
+        .filter_map(|e| {
+            let is_dir = e.file_type().is_ok_and(|t| t.is_dir());
+            let scanned = if is_dir {
+                depth > 1
+            } else {
+                e.path().extension().is_some_and(|ext| ext == "desktop")
+            };
+            scanned.then(|| (e.path(), is_dir))
+        })
+        .collect();
+    children.sort_unstable();
+    for (child, is_dir) in children {
+        if is_dir {
+            collect_mtimes(&child, depth - 1, mtimes);
+        } else {
+            let mtime = mtime(&child);
+            mtimes.push((child, mtime));
+        }
+    }
+}
+
 impl SkimRun for Apps {
-    fn get(&self) -> Vec<std::sync::Arc<(dyn skim::SkimItem + 'static)>> {
-        let mut ctx = AppInfoContext::new(vec![SearchPath::new(std::path::PathBuf::from("/"), 1)]);
-        ctx.refresh_apps().unwrap(); // must refresh apps before getting them
+    fn get(&self) -> Vec<std::sync::Arc<dyn skim::SkimItem>> {
+        let apps = match AppIndex::load() {
+            Some(index) => {
+                if !index.is_fresh() {
+                    std::thread::spawn(Self::refresh_index);
+                }
+                index.apps
+            }
+            None => AppIndex::build()
+                .map(|index| {
+                    let _ = index.save();
+                    index.apps
+                })
+                .unwrap_or_default(),
+        };
 
-        ctx.get_all_apps()
-            .into_iter()
+        apps.into_iter()
             .map(|a| Arc::new(App::from(a)) as Arc<dyn SkimItem>)
             .collect()
     }
@@ -94,8 +313,14 @@ impl SkimRun for Apps {
             .selected_items
             .first()
             .context("Could not find selected item")?;
+        let app = (**item)
+            .as_any()
+            .downcast_ref::<App>()
+            .context("Selected item is not an app")?;
+        let cmd = app.command().context("App has no command to execute")?;
         if let Ok(fork::Fork::Child) = daemon(false, false) {
-            Command::new(item.output().into_owned())
+            Command::new(&cmd[0])
+                .args(&cmd[1..])
                 .spawn()
                 .context("Failed to spawn app")?;
             sleep(Duration::from_secs(10));
@@ -103,7 +328,139 @@ impl SkimRun for Apps {
         Ok(())
     }
     fn set_options(&self, opts: &mut SkimOptions) {
+        let exe = crate::current_exe();
         opts.preview = Some(String::new());
         opts.preview_window = String::from("left:16");
+        opts.header = Some(String::from(
+            "apps - update: alt-u | uninstall: alt-x (flatpak & snap only)",
+        ));
+        opts.bind.extend(vec![
+            format!("alt-u:execute({exe} apps --update {{}})+accept(--query {{q}} apps)"),
+            format!("alt-x:execute({exe} apps --uninstall {{}})+accept(--query {{q}} apps)"),
+        ]);
+    }
+    fn init(&self, mode: &Mode) -> bool {
+        let (desktop_path, action) = match mode {
+            Mode::Apps {
+                update: Some(path), ..
+            } => (path, PackageAction::Update),
+            Mode::Apps {
+                uninstall: Some(path),
+                ..
+            } => (path, PackageAction::Uninstall),
+            _ => return true,
+        };
+        let app = App::from(applications::App {
+            app_desktop_path: desktop_path.clone(),
+            ..Default::default()
+        });
+        if let Err(e) = action.run(&app) {
+            eprintln!("{e:#}");
+            wait_for_enter();
+        }
+        false
+    }
+}
+
+#[derive(Clone, Copy)]
+enum PackageAction {
+    Update,
+    Uninstall,
+}
+
+impl PackageAction {
+    fn command(self, app: &App) -> anyhow::Result<Command> {
+        let mut cmd = match app.source() {
+            AppSource::Flatpak { user } => {
+                let mut cmd = Command::new("flatpak");
+                cmd.arg(match self {
+                    Self::Update => "update",
+                    Self::Uninstall => "uninstall",
+                });
+                cmd.arg(if user { "--user" } else { "--system" });
+                cmd
+            }
+            AppSource::Snap => {
+                let mut cmd = Command::new("snap");
+                cmd.arg(match self {
+                    Self::Update => "refresh",
+                    Self::Uninstall => "remove",
+                });
+                cmd
+            }
+            AppSource::Native => anyhow::bail!(
+                "{} is not a flatpak or snap app, use your package manager instead",
+                app.id()
+            ),
+        };
+        cmd.arg(app.id());
+        Ok(cmd)
+    }
+    fn run(self, app: &App) -> anyhow::Result<()> {
+        let status = self
+            .command(app)?
+            .status()
+            .context("Failed to run package manager")?;
+        anyhow::ensure!(status.success(), "Package manager exited with {status}");
+        Ok(())
+    }
+}
+
+/// Keeps the output on screen until the user acknowledges it, since skim redraws on return
+fn wait_for_enter() {
+    eprintln!("Press enter to continue");
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn mtimes_of_scanned_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["a/b", "c"] {
+            fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        for file in [
+            "top.desktop",
+            "notes.txt",
+            "a/app.desktop",
+            "a/b/deep.desktop",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let listed = |depth| {
+            let mut mtimes = Vec::new();
+            collect_mtimes(root, depth, &mut mtimes);
+            mtimes
+                .into_iter()
+                .map(|(path, _)| {
+                    path.strip_prefix(root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+        // Only the entries of the root are scanned, not its subdirectories
+        assert_eq!(listed(1), ["", "top.desktop"]);
+        assert_eq!(listed(2), ["", "a", "a/app.desktop", "c", "top.desktop"]);
+        assert_eq!(
+            listed(3),
+            [
+                "",
+                "a",
+                "a/app.desktop",
+                "a/b",
+                "a/b/deep.desktop",
+                "c",
+                "top.desktop"
+            ]
+        );
+        assert_eq!(listed(0), [""]);
     }
 }
@@ -0,0 +1,251 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context as _, Result};
+use rink_core::{CURRENCY_FILE, ast, loader::gnu_units};
+
+use crate::xdg;
+
+pub static DEFAULT_RATES_URL: &str = "https://rinkcalc.app/data/currency.json";
+/// Default time-to-live of the cached rates, in seconds
+pub static DEFAULT_RATES_TTL: u64 = 6 * 60 * 60;
+static CACHE_FILE: &str = "currency.json";
+static FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where currency rates come from and how long a cached copy stays valid.
+#[derive(Clone, Debug)]
+pub struct RatesSource {
+    /// An http(s) URL, or a path to a local JSON file
+    pub url: String,
+    pub ttl: Duration,
+}
+
+impl Default for RatesSource {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_RATES_URL.to_string(),
+            ttl: Duration::from_secs(DEFAULT_RATES_TTL),
+        }
+    }
+}
+
+/// Currency definitions ready to be loaded into a rink context.
+pub struct Rates {
+    pub defs: ast::Defs,
+    /// When the live rates were fetched, `None` when only the bundled ones are available
+    pub fetched: Option<SystemTime>,
+}
+
+impl Rates {
+    /// Human readable age of the rates, for the header
+    #[must_use]
+    pub fn age(&self) -> String {
+        let Some(fetched) = self.fetched else {
+            return String::from("bundled");
+        };
+        let secs = fetched.elapsed().unwrap_or_default().as_secs();
+        match secs {
+            0..60 => String::from("just now"),
+            60..3600 => format!("{}m old", secs / 60),
+            3600..86400 => format!("{}h old", secs / 3600),
+            _ => format!("{}d old", secs / 86400),
+        }
+    }
+}
+
+impl RatesSource {
+    fn cache_path() -> PathBuf {
+        xdg::cache_dir().join(CACHE_FILE)
+    }
+
+    /// Modification time of the cached copy, if any
+    fn cached_at(cache: &Path) -> Option<SystemTime> {
+        fs::metadata(cache).and_then(|m| m.modified()).ok()
+    }
+
+    /// Returns true if there is no cached copy, or if it is older than the TTL
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        self.is_stale_at(&Self::cache_path())
+    }
+
+    fn is_stale_at(&self, cache: &Path) -> bool {
+        Self::cached_at(cache)
+            .and_then(|t| t.elapsed().ok())
+            .is_none_or(|age| age > self.ttl)
+    }
+
+    /// Loads the rates without touching the network.
+    /// Falls back to the bundled definitions when the cache is missing or unreadable.
+    #[must_use]
+    pub fn load(&self) -> Rates {
+        Self::load_from(&Self::cache_path())
+    }
+
+    fn load_from(cache: &Path) -> Rates {
+        let mut defs = fs::read(cache)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<ast::Defs>(&bytes).ok());
+        let fetched = defs.as_ref().and(Self::cached_at(cache));
+        if let Some(f) = CURRENCY_FILE {
+            // The bundled file defines the currency base units the live rates rely on
+            defs.get_or_insert_with(|| ast::Defs { defs: Vec::new() })
+                .defs
+                .append(&mut gnu_units::parse_str(f).defs);
+        }
+        Rates {
+            defs: defs.unwrap_or(ast::Defs { defs: Vec::new() }),
+            fetched,
+        }
+    }
+
+    /// Downloads the rates and replaces the cached copy.
+    /// The cache is left untouched if the download or parsing fails.
+    ///
+    /// # Errors
+    /// Returns an error if the rates could not be fetched, parsed or written.
+    pub fn refresh(&self) -> Result<()> {
+        self.refresh_into(&Self::cache_path())
+    }
+
+    fn refresh_into(&self, cache: &Path) -> Result<()> {
+        let bytes = if self.url.starts_with("http://") || self.url.starts_with("https://") {
+            reqwest::blocking::Client::builder()
+                .timeout(FETCH_TIMEOUT)
+                .build()?
+                .get(&self.url)
+                .send()
+                .and_then(reqwest::blocking::Response::error_for_status)
+                .context("Failed to fetch currency rates")?
+                .bytes()
+                .context("Failed to read currency rates")?
+                .to_vec()
+        } else {
+            fs::read(Path::new(&self.url)).context("Failed to read currency rates file")?
+        };
+        serde_json::from_slice::<ast::Defs>(&bytes).context("Failed to parse currency rates")?;
+
+        if let Some(dir) = cache.parent() {
+            fs::create_dir_all(dir).context("Failed to create cache dir")?;
+        }
+        let tmp = cache.with_extension("json.tmp");
+        fs::write(&tmp, bytes).context("Failed to write currency rates")?;
+        fs::rename(&tmp, cache).context("Failed to replace currency rates")?;
+        Ok(())
+    }
+
+    /// Refreshes the cached rates in a background thread if they are stale
+    pub fn refresh_in_background(&self) {
+        if self.is_stale() {
+            let source = self.clone();
+            std::thread::spawn(move || source.refresh());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    static FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/currency.json");
+
+    fn source(ttl: u64) -> RatesSource {
+        RatesSource {
+            url: FIXTURE.to_string(),
+            ttl: Duration::from_secs(ttl),
+        }
+    }
+
+    fn names(rates: &Rates) -> Vec<&str> {
+        rates.defs.defs.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    #[test]
+    fn load_fresh_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join(CACHE_FILE);
+        fs::copy(FIXTURE, &cache).unwrap();
+
+        assert!(!source(DEFAULT_RATES_TTL).is_stale_at(&cache));
+        let rates = RatesSource::load_from(&cache);
+        assert!(rates.fetched.is_some());
+        assert_eq!(rates.age(), "just now");
+        // Live rates first, then the bundled base units
+        assert_eq!(names(&rates)[..2], ["USD", "JPY"]);
+        assert!(names(&rates).contains(&"EUR"));
+    }
+
+    #[test]
+    fn load_stale_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join(CACHE_FILE);
+        fs::copy(FIXTURE, &cache).unwrap();
+        let two_days_ago = SystemTime::now() - Duration::from_hours(48);
+        File::options()
+            .write(true)
+            .open(&cache)
+            .unwrap()
+            .set_modified(two_days_ago)
+            .unwrap();
+
+        assert!(source(DEFAULT_RATES_TTL).is_stale_at(&cache));
+        // Stale rates are still used until a refresh succeeds
+        let rates = RatesSource::load_from(&cache);
+        assert_eq!(rates.age(), "2d old");
+        assert_eq!(names(&rates)[..2], ["USD", "JPY"]);
+    }
+
+    #[test]
+    fn load_bundled_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join(CACHE_FILE);
+        assert!(source(DEFAULT_RATES_TTL).is_stale_at(&cache));
+
+        let rates = RatesSource::load_from(&cache);
+        assert!(rates.fetched.is_none());
+        assert_eq!(rates.age(), "bundled");
+        assert!(names(&rates).contains(&"EUR"));
+        assert!(!names(&rates).contains(&"JPY"));
+
+        // An unreadable cache falls back the same way
+        fs::write(&cache, "not json").unwrap();
+        let rates = RatesSource::load_from(&cache);
+        assert!(rates.fetched.is_none());
+        assert!(!names(&rates).contains(&"JPY"));
+    }
+
+    #[test]
+    fn refresh_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("nested").join(CACHE_FILE);
+        source(0).refresh_into(&cache).unwrap();
+        assert_eq!(fs::read(&cache).unwrap(), fs::read(FIXTURE).unwrap());
+        assert_eq!(names(&RatesSource::load_from(&cache))[..2], ["USD", "JPY"]);
+    }
+
+    #[test]
+    fn refresh_keeps_cache_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join(CACHE_FILE);
+        fs::copy(FIXTURE, &cache).unwrap();
+        let broken = dir.path().join("broken.json");
+        fs::write(&broken, "{").unwrap();
+
+        let source = RatesSource {
+            url: broken.display().to_string(),
+            ..source(0)
+        };
+        assert!(source.refresh_into(&cache).is_err());
+        let missing = RatesSource {
+            url: dir.path().join("missing.json").display().to_string(),
+            ..source
+        };
+        assert!(missing.refresh_into(&cache).is_err());
+        assert_eq!(fs::read(&cache).unwrap(), fs::read(FIXTURE).unwrap());
+    }
+}
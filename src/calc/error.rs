@@ -0,0 +1,144 @@
+use std::fmt::Display;
+
+use rink_core::output::QueryError;
+
+use super::history::Substituted;
+
+/// A failed evaluation, with the position of the offending token when it can be located.
+#[derive(Debug, Clone)]
+pub struct EvalError {
+    pub expr: String,
+    pub message: String,
+    /// Column (in chars) of the offending token in `expr`
+    pub position: Option<usize>,
+}
+
+impl EvalError {
+    /// `query` is what rink evaluated, the right-hand side of `expr` starting at char column
+    /// `offset` with its history references replaced
+    pub(crate) fn new(expr: &str, err: &QueryError, query: &Substituted, offset: usize) -> Self {
+        let message = err.to_string();
+        // Rink does not track spans, so locate the offending token from the error itself
+        let needle = match err {
+            QueryError::NotFound(e) => Some(e.got.as_str()),
+            _ => message
+                .rsplit_once(", got ")
+                .map(|(_, got)| got.trim_matches('`')),
+        };
+        let text = &query.query;
+        let column = match needle {
+            Some("eof") => Some(text.trim_end().chars().count()),
+            Some(needle) if !needle.is_empty() => {
+                text.find(needle).map(|byte| text[..byte].chars().count())
+            }
+            _ => None,
+        };
+        let position = column.map(|column| offset + query.column(column));
+        Self {
+            expr: expr.to_string(),
+            message: first_line(&message).to_string(),
+            position,
+        }
+    }
+
+    /// A single line with the expression, a caret (`‸`) inserted at the error, and the message
+    #[must_use]
+    pub fn pretty(&self) -> String {
+        match self.position {
+            Some(pos) => {
+                let (before, after) = self
+                    .expr
+                    .char_indices()
+                    .nth(pos)
+                    .map_or((self.expr.as_str(), ""), |(byte, _)| {
+                        self.expr.split_at(byte)
+                    });
+                format!("{before}‸{after} - {}", self.message)
+            }
+            None => self.message.clone(),
+        }
+    }
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or_default()
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some(pos) => write!(f, "{} (at column {})", self.message, pos + 1),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{eval_with, history::Entry, history::History, time};
+
+    fn pretty(expr: &str, results: &[&str]) -> String {
+        let history = History {
+            entries: results
+                .iter()
+                .map(|result| Entry {
+                    expr: String::new(),
+                    result: (*result).to_string(),
+                    timestamp: 0,
+                })
+                .collect(),
+        };
+        let mut ctx = rink_core::simple_context().unwrap();
+        let clock = time::parse_clock("2026-10-19T09:30:00+02:00").unwrap();
+        eval_with(&mut ctx, expr, &history, clock)
+            .unwrap_err()
+            .pretty()
+    }
+
+    #[test]
+    fn caret_at_token() {
+        assert_eq!(
+            pretty("1 + foo", &[]),
+            "1 + ‸foo - No such unit foo, did you mean foot?"
+        );
+        assert_eq!(
+            pretty("3 meter +", &[]),
+            "3 meter +‸ - Expected term, got eof"
+        );
+    }
+
+    #[test]
+    fn caret_in_assignment_rhs() {
+        assert_eq!(
+            pretty("foo = 2 * foo", &[]),
+            "foo = 2 * ‸foo - No such unit foo, did you mean foot?"
+        );
+        assert_eq!(
+            pretty("  x =  1 +  ", &[]),
+            "  x =  1 +‸   - Expected term, got eof"
+        );
+    }
+
+    #[test]
+    fn caret_through_substitution() {
+        // A token of a substituted result points at its reference
+        assert_eq!(
+            pretty("_ + foo", &["3 meter"]),
+            "_ + ‸foo - No such unit foo, did you mean foot?"
+        );
+        assert_eq!(
+            pretty("_1 * meter + foo", &["2", "3 meter"]),
+            "_1 * meter + ‸foo - No such unit foo, did you mean foot?"
+        );
+        assert_eq!(
+            pretty("x = _ + foo", &["3 meter"]),
+            "x = _ + ‸foo - No such unit foo, did you mean foot?"
+        );
+        assert_eq!(
+            pretty("_ + 1", &["3 foo"]),
+            "‸_ + 1 - No such unit foo, did you mean foot?"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::{Arc, atomic::AtomicUsize};
+
+use skim::{
+    ItemPreview, PreviewContext, SkimItem,
+    prelude::{Sender, SkimItemReceiver, bounded, unbounded},
+    reader::CommandCollector,
+};
+
+use super::{Calc, history::History};
+
+/// A line of the result, the preview lists all the representations of the result
+struct ResultItem {
+    line: String,
+    preview: String,
+}
+
+impl SkimItem for ResultItem {
+    fn text(&self) -> std::borrow::Cow<'_, str> {
+        std::borrow::Cow::Borrowed(&self.line)
+    }
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        ItemPreview::Text(self.preview.clone())
+    }
+}
+
+/// Evaluates the interactive queries in-process.
+/// Skim hands every query change to the command collector, so instead of spawning
+/// `calc --eval` for each keystroke, the rink context is built once and reused.
+pub struct Evaluator {
+    calc: Calc,
+    ctx: rink_core::Context,
+    history: History,
+}
+
+impl Evaluator {
+    #[must_use]
+    pub fn new(calc: Calc, ctx: rink_core::Context) -> Self {
+        Self {
+            calc,
+            ctx,
+            history: History::load(),
+        }
+    }
+}
+
+impl CommandCollector for Evaluator {
+    fn invoke(
+        &mut self,
+        cmd: &str,
+        _components_to_stop: Arc<AtomicUsize>,
+    ) -> (SkimItemReceiver, Sender<i32>) {
+        let (tx_item, rx_item) = unbounded();
+        // Evaluation is synchronous, there is nothing to interrupt
+        let (tx_interrupt, _) = bounded(1);
+        if !cmd.trim().is_empty() {
+            let result = super::eval_with(&mut self.ctx, cmd, &self.history, self.calc.clock);
+            let preview = match &result {
+                // With --all the representations are already listed
+                Ok(reply) if !self.calc.format.all => self.calc.preview(reply),
+                _ => String::new(),
+            };
+            for line in self.calc.render(result) {
+                let item = ResultItem {
+                    line,
+                    preview: preview.clone(),
+                };
+                let _ = tx_item.send(Arc::new(item) as Arc<dyn SkimItem>);
+            }
+        }
+        (rx_item, tx_interrupt)
+    }
+}
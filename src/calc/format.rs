@@ -0,0 +1,533 @@
+use rink_core::{
+    output::{NumberParts, QueryReply},
+    types::{BigInt, Number, Numeric},
+};
+
+use super::time;
+
+/// How numbers are written
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Notation {
+    /// Rink's own formatting
+    #[default]
+    Auto,
+    /// `1.234e5`
+    Scientific,
+    /// Like scientific, with exponents that are multiples of 3: `123.4e3`
+    Engineering,
+}
+
+/// Base used for integer results
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Base {
+    #[default]
+    Dec,
+    Hex,
+    Oct,
+    Bin,
+}
+
+impl Base {
+    fn radix(self) -> u32 {
+        match self {
+            Base::Dec => 10,
+            Base::Hex => 16,
+            Base::Oct => 8,
+            Base::Bin => 2,
+        }
+    }
+    fn prefix(self) -> &'static str {
+        match self {
+            Base::Dec => "",
+            Base::Hex => "0x",
+            Base::Oct => "0o",
+            Base::Bin => "0b",
+        }
+    }
+}
+
+/// Output options of the calculator
+#[derive(Clone, Debug, Default, clap::Args)]
+pub struct OutputFormat {
+    /// Number of significant digits
+    #[arg(long)]
+    pub digits: Option<usize>,
+    #[arg(long, value_enum, default_value_t)]
+    pub notation: Notation,
+    /// Base for integer results, other results stay decimal
+    #[arg(long, value_enum, default_value_t)]
+    pub base: Base,
+    /// Copy results without their unit
+    #[arg(long, default_value = "false")]
+    pub no_units: bool,
+    /// List the result in all its representations, the selected one gets copied
+    #[arg(long, default_value = "false")]
+    pub all: bool,
+    /// Timezones of the world clock shown for dates, by name or city
+    #[arg(long, value_delimiter = ',', value_parser = time::parse_zone, default_values = time::DEFAULT_TIMEZONES)]
+    pub timezones: Vec<chrono_tz::Tz>,
+}
+
+impl OutputFormat {
+    /// The command line flags matching these options, to forward them to a new process
+    #[must_use]
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(d) = self.digits {
+            args.extend(["--digits".to_string(), d.to_string()]);
+        }
+        if self.notation != Notation::default() {
+            args.extend(["--notation".to_string(), value_name(&self.notation)]);
+        }
+        if self.base != Base::default() {
+            args.extend(["--base".to_string(), value_name(&self.base)]);
+        }
+        if self.no_units {
+            args.push("--no-units".to_string());
+        }
+        if self.all {
+            args.push("--all".to_string());
+        }
+        let zones: Vec<&str> = self.timezones.iter().map(|tz| tz.name()).collect();
+        if zones != time::DEFAULT_TIMEZONES {
+            args.extend(["--timezones".to_string(), zones.join(",")]);
+        }
+        args
+    }
+
+    /// Formats a reply for display, numbers keep their unit and quantity
+    #[must_use]
+    pub fn display(&self, reply: &QueryReply) -> String {
+        match number_parts(reply) {
+            Some(parts) => {
+                let quantity = parts.format("w");
+                let value = with_unit(self.value(parts), parts);
+                if quantity.is_empty() {
+                    value
+                } else {
+                    format!("{value} {quantity}")
+                }
+            }
+            None => reply.to_string(),
+        }
+    }
+
+    /// Formats a reply for the clipboard and the history
+    #[must_use]
+    pub fn copied(&self, reply: &QueryReply) -> String {
+        match number_parts(reply) {
+            Some(parts) if self.no_units => self.value(parts),
+            Some(parts) => with_unit(self.value(parts), parts),
+            // Without the humanized "(in 3 days)" suffix
+            None if let QueryReply::Date(date) = reply => date.string.clone(),
+            None => reply.to_string(),
+        }
+    }
+
+    /// Every representation of a numeric reply, as `(label, value)`
+    #[must_use]
+    pub fn representations(&self, reply: &QueryReply) -> Vec<(&'static str, String)> {
+        match reply {
+            QueryReply::Date(date) => return time::world_clock(&date.rfc3339, &self.timezones),
+            QueryReply::Duration(duration) => return durations(&duration.raw, self.digits),
+            _ => {}
+        }
+        let Some(parts) = number_parts(reply) else {
+            return vec![("result", reply.to_string())];
+        };
+        let unit = |v: String| {
+            if self.no_units {
+                v
+            } else {
+                with_unit(v, parts)
+            }
+        };
+        let mut reprs = Vec::new();
+        if let Some(x) = to_exact(parts) {
+            reprs.push(("decimal", unit(plain(&x, self.digits))));
+            reprs.push(("scientific", unit(scientific(&x, self.digits))));
+            reprs.push(("engineering", unit(engineering(&x, self.digits))));
+        }
+        if let Some(fraction) = to_fraction(parts) {
+            reprs.push(("fraction", unit(fraction)));
+        }
+        if let Some(int) = to_int(parts) {
+            for (label, base) in [("hex", Base::Hex), ("oct", Base::Oct), ("bin", Base::Bin)] {
+                reprs.push((label, unit(radix(&int, base))));
+            }
+        }
+        if let Some(raw) = parts.raw_value.as_ref().filter(|r| !r.dimless()) {
+            let value = Exact::new(&raw.value).map_or_else(
+                || raw.to_parts_simple().format("n"),
+                |x| plain(&x, self.digits),
+            );
+            reprs.push((
+                "base units",
+                if self.no_units {
+                    value
+                } else {
+                    format!("{value} {}", Number::unit_to_string(&raw.unit))
+                },
+            ));
+        }
+        reprs
+    }
+
+    fn value(&self, parts: &NumberParts) -> String {
+        if self.base != Base::Dec
+            && let Some(int) = to_int(parts)
+        {
+            return radix(&int, self.base);
+        }
+        match (self.notation, to_exact(parts)) {
+            (Notation::Scientific, Some(x)) => scientific(&x, self.digits),
+            (Notation::Engineering, Some(x)) => engineering(&x, self.digits),
+            (Notation::Auto, Some(x)) if self.digits.is_some() => plain(&x, self.digits),
+            _ => parts.format("n"),
+        }
+    }
+}
+
+/// A duration, from its value in seconds, in each unit from seconds to years
+fn durations(raw: &NumberParts, digits: Option<usize>) -> Vec<(&'static str, String)> {
+    // The displayed value may carry an SI prefix, the raw one is in seconds
+    let Some(seconds) = raw.raw_value.as_ref().and_then(|n| Exact::new(&n.value)) else {
+        return vec![("result", raw.to_string())];
+    };
+    [
+        ("seconds", 1u64),
+        ("minutes", 60),
+        ("hours", 3600),
+        ("days", 86400),
+        ("weeks", 604_800),
+        ("years", 31_556_952),
+    ]
+    .into_iter()
+    .map(|(label, unit)| {
+        let value = Exact {
+            num: &seconds.num * &BigInt::one(),
+            den: &seconds.den * &BigInt::from(unit),
+        };
+        (label, plain(&value, digits))
+    })
+    .collect()
+}
+
+fn value_name(v: &impl clap::ValueEnum) -> String {
+    v.to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn number_parts(reply: &QueryReply) -> Option<&NumberParts> {
+    match reply {
+        QueryReply::Number(parts) => Some(parts),
+        QueryReply::Conversion(conv) => Some(&conv.value),
+        _ => None,
+    }
+}
+
+fn with_unit(value: String, parts: &NumberParts) -> String {
+    let unit = parts.format("u");
+    if unit.is_empty() {
+        value
+    } else {
+        format!("{value} {unit}")
+    }
+}
+
+/// The displayed value as an integer, if it is one
+fn to_int(parts: &NumberParts) -> Option<BigInt> {
+    let exact = parts.exact_value.as_ref()?;
+    let digits = exact.strip_prefix('-').unwrap_or(exact);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    BigInt::from_str_radix(exact, 10).ok()
+}
+
+fn radix(int: &BigInt, base: Base) -> String {
+    let s = int.inner().to_str_radix(base.radix());
+    match s.strip_prefix('-') {
+        Some(abs) => format!("-{}{abs}", base.prefix()),
+        None => format!("{}{s}", base.prefix()),
+    }
+}
+
+/// The displayed value as a float, parsed back from rink's approximate representation
+fn to_f64(parts: &NumberParts) -> Option<f64> {
+    let s = parts.approx_value.as_ref().or(parts.exact_value.as_ref())?;
+    if let Some((num, den)) = s.split_once('/') {
+        return Some(num.trim().parse::<f64>().ok()? / den.trim().parse::<f64>().ok()?);
+    }
+    // Recurring decimals are written `0.[3]...` or `0.[142857, period 6]...`,
+    // repeat the group enough times to fill a float
+    let s = match s.split_once('[') {
+        Some((head, rest)) => {
+            let group = rest.split([']', ',']).next().unwrap_or_default();
+            let mut expanded = head.to_string();
+            while !group.is_empty() && expanded.len() < 24 {
+                expanded.push_str(group);
+            }
+            expanded
+        }
+        None => s.clone(),
+    };
+    s.parse().ok()
+}
+
+/// The displayed value, exactly
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_exact(parts: &NumberParts) -> Option<Exact> {
+    let raw = &parts.raw_value.as_ref()?.value;
+    let mut exact = Exact::new(raw)?;
+    // The raw value is in base units, the displayed one may have an SI prefix,
+    // which scales it by a power of ten
+    let shown = to_f64(parts)?.abs();
+    let base = raw.to_f64().abs();
+    if shown != 0.0 && base != 0.0 {
+        let shift = (shown.log10() - base.log10()).round();
+        if !shift.is_finite() {
+            return None;
+        }
+        let scale = BigInt::from(10u64).pow(shift.abs() as u32);
+        if shift > 0.0 {
+            exact.num = &exact.num * &scale;
+        } else {
+            exact.den = &exact.den * &scale;
+        }
+    }
+    Some(exact)
+}
+
+/// A number as a fraction, with a positive denominator
+struct Exact {
+    num: BigInt,
+    den: BigInt,
+}
+
+impl Exact {
+    fn new(value: &Numeric) -> Option<Self> {
+        if let Numeric::Float(x) = value
+            && !x.is_finite()
+        {
+            return None;
+        }
+        let (num, den) = value.to_rational();
+        Some(Self { num, den })
+    }
+
+    fn is_zero(&self) -> bool {
+        self.num == BigInt::zero()
+    }
+
+    fn sign(&self) -> &'static str {
+        if self.num < BigInt::zero() { "-" } else { "" }
+    }
+
+    /// The absolute value rounded half up to `d` significant digits,
+    /// as its digits and the power of ten of the first one. Must not be zero.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn significant(&self, d: usize) -> (String, i64) {
+        let d = d.max(1);
+        let num = self.num.abs();
+        let ten = |exp: i64| BigInt::from(10u64).pow(exp.unsigned_abs() as u32);
+        // num / den is in [10^exp, 10^(exp + 1)) once adjusted
+        let mut exp = num.to_string().len() as i64 - self.den.to_string().len() as i64;
+        let below = if exp >= 0 {
+            num < &self.den * &ten(exp)
+        } else {
+            &num * &ten(exp) < self.den
+        };
+        if below {
+            exp -= 1;
+        }
+        let shift = d as i64 - 1 - exp;
+        let (n, m) = if shift >= 0 {
+            (&num * &ten(shift), &self.den * &BigInt::one())
+        } else {
+            (num, &self.den * &ten(shift))
+        };
+        let mut q = &n / &m;
+        if &(&n % &m) * &BigInt::from(2u64) >= m {
+            q = &q + &BigInt::one();
+        }
+        let mut digits = q.to_string();
+        // Rounding up 9.99 gives 10.0
+        if digits.len() > d {
+            digits.truncate(d);
+            exp += 1;
+        }
+        (digits, exp)
+    }
+}
+
+/// `digits` with a decimal point after the first `int_digits`, padded with zeros
+fn with_point(digits: &str, int_digits: usize) -> String {
+    if digits.len() <= int_digits {
+        format!("{digits:0<int_digits$}")
+    } else {
+        format!("{}.{}", &digits[..int_digits], &digits[int_digits..])
+    }
+}
+
+/// Drops the trailing zeros of a decimal fraction
+fn trim_zeros(s: String, digits: Option<usize>) -> String {
+    if digits.is_some() || !s.contains('.') {
+        return s;
+    }
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// The displayed value as a fraction, when it is not an integer
+fn to_fraction(parts: &NumberParts) -> Option<String> {
+    if let Some(fraction) = parts.exact_value.as_ref().filter(|e| e.contains('/')) {
+        return Some(fraction.clone());
+    }
+    // Without a display unit the raw value is the displayed one
+    let raw = parts.raw_value.as_ref().filter(|_| parts.unit.is_none())?;
+    let (num, den) = raw.value.to_rational();
+    (den != BigInt::one()).then(|| format!("{num}/{den}"))
+}
+
+/// Significant digits kept when none are requested
+static DEFAULT_DIGITS: usize = 15;
+
+fn scientific(x: &Exact, digits: Option<usize>) -> String {
+    if x.is_zero() {
+        return format!("{:.p$e}", 0.0, p = digits.unwrap_or(1).saturating_sub(1));
+    }
+    let (sig, exp) = x.significant(digits.unwrap_or(DEFAULT_DIGITS));
+    let mantissa = trim_zeros(with_point(&sig, 1), digits);
+    format!("{}{mantissa}e{exp}", x.sign())
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn engineering(x: &Exact, digits: Option<usize>) -> String {
+    if x.is_zero() {
+        return scientific(x, digits);
+    }
+    let (sig, exp) = x.significant(digits.unwrap_or(DEFAULT_DIGITS));
+    let eng = exp.div_euclid(3) * 3;
+    // The mantissa has 1 to 3 integer digits
+    let mantissa = trim_zeros(with_point(&sig, (exp - eng) as usize + 1), digits);
+    format!("{}{mantissa}e{eng}", x.sign())
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn plain(x: &Exact, digits: Option<usize>) -> String {
+    if x.is_zero() {
+        return "0".to_string();
+    }
+    // Rounded to the significant digits, printed without exponent
+    let (sig, exp) = x.significant(digits.unwrap_or(DEFAULT_DIGITS));
+    let value = if exp >= 0 {
+        with_point(&sig, exp as usize + 1)
+    } else {
+        format!("0.{}{sig}", "0".repeat((-exp - 1) as usize))
+    };
+    format!("{}{}", x.sign(), trim_zeros(value, digits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(query: &str) -> QueryReply {
+        let mut ctx = rink_core::simple_context().unwrap();
+        let clock = time::parse_clock("2026-10-19T09:30:00+02:00").unwrap();
+        time::eval(&mut ctx, query, clock).unwrap()
+    }
+
+    fn format(digits: Option<usize>, notation: Notation) -> OutputFormat {
+        OutputFormat {
+            digits,
+            notation,
+            ..OutputFormat::default()
+        }
+    }
+
+    fn copied(format: &OutputFormat, query: &str) -> String {
+        format.copied(&reply(query))
+    }
+
+    #[test]
+    fn exact_digits() {
+        // Rink only displays 7 significant digits
+        let nine = format(Some(9), Notation::Auto);
+        assert_eq!(copied(&nine, "12345.678 m"), "12.3456780 kilometer");
+        assert_eq!(copied(&nine, "2^80"), "1208925820000000000000000");
+        assert_eq!(copied(&nine, "1/3"), "0.333333333");
+        assert_eq!(copied(&nine, "-2 mm / 3"), "-666.666667 micrometer");
+        assert_eq!(copied(&format(Some(2), Notation::Auto), "999.7"), "1000");
+        assert_eq!(copied(&format(Some(3), Notation::Auto), "0"), "0");
+        // Without digits the notation is rink's own
+        assert_eq!(
+            copied(&OutputFormat::default(), "12345.678 m"),
+            "approx. 12.34567 kilometer"
+        );
+    }
+
+    #[test]
+    fn scientific_notation() {
+        let auto = format(None, Notation::Scientific);
+        assert_eq!(copied(&auto, "2^80"), "1.20892581961463e24");
+        assert_eq!(copied(&auto, "-0.00125"), "-1.25e-3");
+        assert_eq!(copied(&auto, "0"), "0e0");
+        let four = format(Some(4), Notation::Scientific);
+        assert_eq!(copied(&four, "2^80"), "1.209e24");
+        assert_eq!(copied(&four, "12345.678 m"), "1.235e1 kilometer");
+        assert_eq!(copied(&four, "9.9996"), "1.000e1");
+    }
+
+    #[test]
+    fn engineering_notation() {
+        let auto = format(None, Notation::Engineering);
+        assert_eq!(copied(&auto, "2^80"), "1.20892581961463e24");
+        assert_eq!(copied(&auto, "2^81"), "2.41785163922926e24");
+        assert_eq!(copied(&auto, "0.00125"), "1.25e-3");
+        assert_eq!(copied(&auto, "-123456"), "-123.456e3");
+        let two = format(Some(2), Notation::Engineering);
+        assert_eq!(copied(&two, "123456"), "120e3");
+        assert_eq!(copied(&two, "0.000123456 m"), "120e0 micrometer");
+    }
+
+    #[test]
+    fn no_units() {
+        let format = OutputFormat {
+            no_units: true,
+            ..OutputFormat::default()
+        };
+        assert_eq!(copied(&format, "1500 m"), "1.5");
+        assert_eq!(
+            format.representations(&reply("12345.678 m")),
+            [
+                ("decimal", "12.345678".to_string()),
+                ("scientific", "1.2345678e1".to_string()),
+                ("engineering", "12.345678e0".to_string()),
+                ("base units", "12345.678".to_string()),
+            ]
+        );
+        let with_units = OutputFormat::default().representations(&reply("12345.678 m"));
+        assert_eq!(
+            with_units.last(),
+            Some(&("base units", "12345.678 m".to_string()))
+        );
+    }
+
+    #[test]
+    fn duration_units() {
+        let reprs = OutputFormat::default().representations(&reply("90 minutes"));
+        assert_eq!(
+            reprs,
+            [
+                ("seconds", "5400".to_string()),
+                ("minutes", "90".to_string()),
+                ("hours", "1.5".to_string()),
+                ("days", "0.0625".to_string()),
+                ("weeks", "0.00892857142857143".to_string()),
+                ("years", "0.000171119187936782".to_string()),
+            ]
+        );
+    }
+}
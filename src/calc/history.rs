@@ -0,0 +1,396 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File},
+    io::BufReader,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use skim::{ItemPreview, PreviewContext, SkimItem, SkimOutput};
+
+use crate::{Mode, SkimRun, xdg};
+
+use super::Calc;
+
+static HISTORY_FILE: &str = "calc_history.json";
+static VARIABLES_FILE: &str = "calc_variables.json";
+/// Oldest entries are dropped past this length
+static MAX_ENTRIES: usize = 1000;
+
+/// A single evaluated expression
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Entry {
+    pub expr: String,
+    pub result: String,
+    /// Unix timestamp, in seconds
+    pub timestamp: i64,
+}
+
+/// Evaluated expressions, most recent first.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(transparent)]
+pub struct History {
+    pub entries: Vec<Entry>,
+}
+
+/// User-defined variables, mapping a name to its rink definition.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(transparent)]
+pub struct Variables {
+    pub defs: BTreeMap<String, String>,
+}
+
+fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    File::open(xdg::state_dir().join(name))
+        .ok()
+        .and_then(|f| serde_json::from_reader(BufReader::new(f)).ok())
+        .unwrap_or_default()
+}
+
+fn save<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    let path: PathBuf = xdg::state_dir().join(name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("Failed to create state dir")?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    serde_json::to_writer_pretty(File::create(&tmp)?, value)
+        .with_context(|| format!("Failed to write {name}"))?;
+    fs::rename(&tmp, &path).with_context(|| format!("Failed to replace {name}"))?;
+    Ok(())
+}
+
+impl History {
+    #[must_use]
+    pub fn load() -> Self {
+        load(HISTORY_FILE)
+    }
+
+    /// # Errors
+    /// Returns an error if the history could not be written.
+    pub fn save(&self) -> Result<()> {
+        save(HISTORY_FILE, self)
+    }
+
+    /// Records a new result at the front of the history
+    pub fn push(&mut self, expr: &str, result: &str) {
+        self.entries.insert(
+            0,
+            Entry {
+                expr: expr.to_string(),
+                result: result.to_string(),
+                timestamp: chrono::Local::now().timestamp(),
+            },
+        );
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    /// The `n`-th most recent result, `_` is 0, `_1` is 1, ...
+    #[must_use]
+    pub fn get(&self, n: usize) -> Option<&str> {
+        self.entries.get(n).map(|e| e.result.as_str())
+    }
+}
+
+impl Variables {
+    #[must_use]
+    pub fn load() -> Self {
+        load(VARIABLES_FILE)
+    }
+
+    /// # Errors
+    /// Returns an error if the variables could not be written.
+    pub fn save(&self) -> Result<()> {
+        save(VARIABLES_FILE, self)
+    }
+
+    /// The variables as a GNU units file, ready to be loaded into a rink context
+    #[must_use]
+    pub fn to_units(&self) -> String {
+        self.defs
+            .iter()
+            .fold(String::new(), |mut out, (name, def)| {
+                let _ = writeln!(out, "{name} {def}");
+                out
+            })
+    }
+}
+
+/// Splits `name = definition`, if `expr` is an assignment
+#[must_use]
+pub fn split_assignment(expr: &str) -> Option<(&str, &str)> {
+    let (name, def) = expr.split_once('=')?;
+    let name = name.trim();
+    let def = def.trim();
+    let mut chars = name.chars();
+    let valid_name = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !is_history_ref(name);
+    (valid_name && !def.is_empty() && !def.starts_with('=')).then_some((name, def))
+}
+
+fn is_history_ref(name: &str) -> bool {
+    name.strip_prefix('_')
+        .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Replaces `_`, `_1`, `_2`, ... with the matching results from the history.
+/// Underscores that are part of an identifier are left alone.
+#[must_use]
+pub fn substitute(expr: &str, history: &History) -> String {
+    substitute_with(expr, |_, n| history.get(n).map(String::from)).query
+}
+
+/// A query with its history references replaced by their results
+#[derive(Debug)]
+pub struct Substituted {
+    pub query: String,
+    /// Column of the original expression each char of `query` comes from, then its end
+    columns: Vec<usize>,
+}
+
+impl Substituted {
+    /// Maps a char column of `query` back to the original expression,
+    /// columns inside a substituted result map to its reference
+    #[must_use]
+    pub fn column(&self, column: usize) -> usize {
+        self.columns
+            .get(column)
+            .or(self.columns.last())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Replaces the history references of `expr` with what `resolve` returns for their char
+/// column and index (`_` is 0), references it returns `None` for are left alone.
+pub fn substitute_with(
+    expr: &str,
+    mut resolve: impl FnMut(usize, usize) -> Option<String>,
+) -> Substituted {
+    let mut out = String::with_capacity(expr.len());
+    let mut columns = Vec::with_capacity(expr.len() + 1);
+    let mut chars = expr.chars().enumerate().peekable();
+    let mut prev_is_ident = false;
+    while let Some((column, c)) = chars.next() {
+        if c == '_' && !prev_is_ident {
+            let digits: String = std::iter::from_fn(|| chars.next_if(|(_, d)| d.is_ascii_digit()))
+                .map(|(_, d)| d)
+                .collect();
+            let next_is_ident = chars
+                .peek()
+                .is_some_and(|(_, n)| n.is_alphanumeric() || *n == '_');
+            let n = if digits.is_empty() {
+                Some(0)
+            } else {
+                digits.parse().ok()
+            };
+            if !next_is_ident && let Some(result) = n.and_then(|n| resolve(column, n)) {
+                let result = format!("({result})");
+                columns.extend(std::iter::repeat_n(column, result.chars().count()));
+                out.push_str(&result);
+                prev_is_ident = false;
+                continue;
+            }
+            out.push('_');
+            out.push_str(&digits);
+            columns.extend(column..=column + digits.len());
+            prev_is_ident = true;
+            continue;
+        }
+        prev_is_ident = c.is_alphanumeric() || c == '_';
+        out.push(c);
+        columns.push(column);
+    }
+    columns.push(expr.chars().count());
+    Substituted {
+        query: out,
+        columns,
+    }
+}
+
+struct HistoryItem {
+    index: usize,
+    entry: Entry,
+}
+
+impl SkimItem for HistoryItem {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Owned(format!("{} = {}", self.entry.expr, self.entry.result))
+    }
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Owned(self.index.to_string())
+    }
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        let date = chrono::DateTime::from_timestamp(self.entry.timestamp, 0)
+            .map(|d| d.with_timezone(&chrono::Local).to_rfc2822())
+            .unwrap_or_default();
+        let reference = if self.index == 0 {
+            String::from("_")
+        } else {
+            format!("_{}", self.index)
+        };
+        ItemPreview::Text(format!(
+            "Expression: {}\nResult: {}\nReference: {}\nDate: {}",
+            self.entry.expr, self.entry.result, reference, date
+        ))
+    }
+}
+
+/// Picker over the calculator history.
+/// Enter copies the stored result, ctrl-r evaluates the expression again.
+pub struct CalcHistory {
+    pub calc: Calc,
+}
+
+impl SkimRun for CalcHistory {
+    fn get(&self) -> Vec<Arc<dyn SkimItem>> {
+        History::load()
+            .entries
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| Arc::new(HistoryItem { index, entry }) as Arc<dyn SkimItem>)
+            .collect()
+    }
+    fn set_options(&self, opts: &mut skim::SkimOptions) {
+        opts.preview = Some(String::new());
+        opts.preview_window = String::from("up:30%");
+        opts.header = Some(String::from("calc history - copy: enter | re-run: ^r"));
+        // Evaluate again with the same rates, format and clock
+        let mut args = self.calc.args();
+        if self.calc.offline {
+            args.push(String::from("--offline"));
+        }
+        opts.bind.extend(vec![format!(
+            "ctrl-r:accept(calc-history --rerun {{}} {})",
+            args.join(" ")
+        )]);
+    }
+    fn run(&self, output: &SkimOutput) -> Result<()> {
+        let index: usize = output
+            .selected_items
+            .first()
+            .context("Could not find selected item")?
+            .output()
+            .parse()?;
+        let history = History::load();
+        let entry = history
+            .entries
+            .get(index)
+            .context("No such history entry")?;
+        super::copy(&entry.result);
+        println!("{}", entry.result);
+        Ok(())
+    }
+    fn init(&self, mode: &Mode) -> bool {
+        let Mode::CalcHistory {
+            rerun: Some(index), ..
+        } = mode
+        else {
+            return true;
+        };
+        let history = History::load();
+        let Some(entry) = history.entries.get(*index) else {
+            eprintln!("No such history entry: {index}");
+            std::process::exit(1);
+        };
+        match self.calc.accept(&entry.expr, None) {
+            Ok(result) => println!("{result}"),
+            Err(e) => {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(results: &[&str]) -> History {
+        History {
+            entries: results
+                .iter()
+                .map(|r| Entry {
+                    expr: String::new(),
+                    result: (*r).to_string(),
+                    timestamp: 0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn substitute_references() {
+        let history = history(&["3 meter", "2", "-1"]);
+        assert_eq!(substitute("_ + 1", &history), "(3 meter) + 1");
+        assert_eq!(substitute("_1*_2", &history), "(2)*(-1)");
+        assert_eq!(substitute("_+_", &history), "(3 meter)+(3 meter)");
+    }
+
+    #[test]
+    fn substitute_identifier_boundaries() {
+        let history = history(&["3 meter", "2"]);
+        // Underscores inside or at the start of identifiers are not references
+        assert_eq!(substitute("a_1 + _1", &history), "a_1 + (2)");
+        assert_eq!(substitute("_1a + _foo", &history), "_1a + _foo");
+        assert_eq!(substitute("x_ * _", &history), "x_ * (3 meter)");
+        assert_eq!(substitute("__", &history), "__");
+    }
+
+    #[test]
+    fn substitute_beyond_history() {
+        let history = history(&["3 meter", "2"]);
+        assert_eq!(substitute("_2 + _1", &history), "_2 + (2)");
+        assert_eq!(
+            substitute("_99999999999999999999999", &history),
+            "_99999999999999999999999"
+        );
+        assert_eq!(substitute("_ + 1", &History::default()), "_ + 1");
+    }
+
+    #[test]
+    fn substitute_columns() {
+        let history = history(&["3 meter", "2"]);
+        let mut columns = Vec::new();
+        let query = substitute_with("é + _1 * a_1", |column, n| {
+            columns.push(column);
+            history.get(n).map(String::from)
+        });
+        assert_eq!(query.query, "é + (2) * a_1");
+        assert_eq!(columns, [4]);
+        // The substituted result maps to its reference, the rest keeps its char column
+        assert_eq!(query.column(0), 0);
+        assert_eq!(query.column(4), 4);
+        assert_eq!(query.column(6), 4);
+        assert_eq!(query.column(8), 7);
+        assert_eq!(query.column(13), 12);
+        assert_eq!(query.column(100), 12);
+    }
+
+    #[test]
+    fn split_assignments() {
+        assert_eq!(split_assignment("x = 3 m"), Some(("x", "3 m")));
+        assert_eq!(
+            split_assignment(" speed_2=  5 m/s "),
+            Some(("speed_2", "5 m/s"))
+        );
+        assert_eq!(split_assignment("x = a == b"), Some(("x", "a == b")));
+        // Comparisons and invalid names are not assignments
+        assert_eq!(split_assignment("x == 3"), None);
+        assert_eq!(split_assignment("x <= 3"), None);
+        assert_eq!(split_assignment("x >= 3"), None);
+        assert_eq!(split_assignment("2 x = 3"), None);
+        assert_eq!(split_assignment("1x = 3"), None);
+        assert_eq!(split_assignment("_1 = 3"), None);
+        assert_eq!(split_assignment("_ = 3"), None);
+        assert_eq!(split_assignment("x ="), None);
+        assert_eq!(split_assignment("= 3"), None);
+        assert_eq!(split_assignment("3 m"), None);
+    }
+}
@@ -0,0 +1,322 @@
+use std::{borrow::Cow, cell::RefCell, fmt::Write as _, path::PathBuf, process::Command, rc::Rc};
+
+use rink_core::{
+    DATES_FILE, DEFAULT_FILE, loader::gnu_units, output::QueryReply, parsing::datetime,
+};
+use skim::{SkimItem, SkimOutput};
+
+use crate::{Mode, SkimRun};
+
+pub mod currency;
+mod error;
+mod evaluator;
+pub mod format;
+pub mod history;
+pub mod prog;
+pub mod time;
+pub mod units;
+
+use currency::RatesSource;
+pub use error::EvalError;
+use format::OutputFormat;
+use history::{History, Variables};
+
+#[derive(Default, Clone)]
+pub struct Calc {
+    pub rates: RatesSource,
+    /// Never fetch currency rates, only use the cached or bundled ones
+    pub offline: bool,
+    pub format: OutputFormat,
+    pub clock: time::Clock,
+}
+
+impl SkimItem for Calc {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::default()
+    }
+}
+
+impl SkimRun for Calc {
+    fn set_options(&self, opts: &mut skim::prelude::SkimOptions) {
+        let exe = crate::current_exe();
+        let args = self.args();
+        let quoted_args = shlex::try_join(args.iter().map(String::as_str)).unwrap_or_default();
+        let history = History::load();
+        let variables = Variables::load();
+        let (ctx, def_errors) = context(self.rates.load(), &variables, &units::user_files());
+        // The collector gets the raw query and evaluates it with the session context
+        opts.cmd = Some(String::from("{}"));
+        opts.cmd_collector = Rc::new(RefCell::new(evaluator::Evaluator::new(self.clone(), ctx)));
+        opts.interactive = true;
+        if !self.format.all {
+            opts.preview = Some(String::new());
+            opts.preview_window = String::from("up:40%");
+        }
+        // Save the result to the history, then start over with the updated header
+        let pick = if self.format.all { " --pick {}" } else { "" };
+        opts.bind.extend(vec![format!(
+            "enter:execute-silent({exe} calc --eval --save --offline {quoted_args}{pick} {{cq}})+accept(calc {})",
+            args.join(" ")
+        )]);
+        opts.header = Some(format!(
+            "calc - previous(_): {}{} | rates: {}",
+            history.get(0).unwrap_or("N/A"),
+            match history.entries.len() {
+                0 | 1 => String::new(),
+                n => format!(" | older: _1.._{}", n - 1),
+            },
+            self.rates.load().age()
+        ));
+        if !def_errors.is_empty() {
+            opts.header = opts.header.take().map(|h| {
+                format!(
+                    "{h} | definitions: {} error(s), see calc --check-defs",
+                    def_errors.len()
+                )
+            });
+        }
+        if !variables.defs.is_empty() {
+            opts.header = opts.header.take().map(|h| {
+                format!(
+                    "{h}\nvariables: {}",
+                    variables
+                        .defs
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            });
+        }
+    }
+    fn run(&self, output: &SkimOutput) -> anyhow::Result<()> {
+        println!("{}", self.accept(&output.cmd, None)?);
+        Ok(())
+    }
+    fn init(&self, mode: &Mode) -> bool {
+        if let Mode::Calc {
+            check_defs: true, ..
+        } = mode
+        {
+            std::process::exit(i32::from(!check_defs(
+                self.rates.load(),
+                &units::user_files(),
+            )));
+        }
+        if let Mode::Calc {
+            eval: true,
+            save,
+            pick,
+            expr,
+            ..
+        } = mode
+        {
+            // The process exits right after printing, a background refresh would be cut short.
+            // Stale rates are fetched first, within the fetch timeout, the cache is used on failure
+            if !self.offline
+                && self.rates.is_stale()
+                && let Err(e) = self.rates.refresh()
+            {
+                eprintln!("{e:#}");
+            }
+            let expr = expr.join(" ");
+            if expr.trim().is_empty() {
+                return false;
+            }
+            if *save {
+                match self.accept(&expr, pick.as_deref()) {
+                    Ok(result) => println!("{result}"),
+                    Err(e) => {
+                        eprintln!("{e:#}");
+                        std::process::exit(1);
+                    }
+                }
+                return false;
+            }
+            match self.eval(&expr) {
+                Ok(result) => {
+                    for line in self.render(Ok(result)) {
+                        println!("{line}");
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e.pretty());
+                    std::process::exit(1);
+                }
+            }
+            return false;
+        }
+        if !self.offline {
+            self.rates.refresh_in_background();
+        }
+        true
+    }
+}
+
+impl Calc {
+    fn eval(&self, expr: &str) -> Result<QueryReply, EvalError> {
+        eval(
+            expr,
+            self.rates.load(),
+            &History::load(),
+            &Variables::load(),
+            self.clock,
+        )
+    }
+
+    /// Lines to show for an evaluation, errors get a caret at the offending token
+    fn render(&self, result: Result<QueryReply, EvalError>) -> Vec<String> {
+        match result {
+            Ok(result) if self.format.all => self
+                .format
+                .representations(&result)
+                .into_iter()
+                .map(|(label, value)| format!("{label}: {value}"))
+                .collect(),
+            Ok(result) => vec![self.format.display(&result)],
+            Err(e) => vec![e.pretty()],
+        }
+    }
+
+    /// All the representations of a result as a table, a world clock for dates
+    fn preview(&self, reply: &QueryReply) -> String {
+        let title = match reply {
+            QueryReply::Date(_) => "world clock",
+            QueryReply::Duration(_) => "duration",
+            _ => "representations",
+        };
+        let reprs = self.format.representations(reply);
+        let width = reprs
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        reprs
+            .iter()
+            .fold(format!("{title}\n"), |mut out, (label, value)| {
+                let _ = writeln!(out, "{label:<width$}  {value}");
+                out
+            })
+    }
+
+    /// Flags to forward to the processes evaluating the query
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--rates-url".to_string(),
+            self.rates.url.clone(),
+            "--rates-ttl".to_string(),
+            self.rates.ttl.as_secs().to_string(),
+        ];
+        args.extend(self.format.to_args());
+        args.extend(self.clock.to_args());
+        args
+    }
+
+    /// Evaluates `expr`, stores it in the history and copies the result.
+    /// `pick` is a line from the representations list, copied instead of the default result.
+    /// Assignments (`name = definition`) also persist the variable.
+    pub(crate) fn accept(&self, expr: &str, pick: Option<&str>) -> anyhow::Result<String> {
+        let reply = self.eval(expr)?;
+        let result = match pick {
+            Some(line) => line
+                .split_once(": ")
+                .map_or(line, |(_, value)| value)
+                .to_string(),
+            None => self.format.copied(&reply),
+        };
+        let mut history = History::load();
+        if let Some((name, def)) = history::split_assignment(expr) {
+            let mut variables = Variables::load();
+            // Resolve `_` now, the definition must not change with the history
+            variables
+                .defs
+                .insert(name.to_string(), history::substitute(def, &history));
+            variables.save()?;
+        }
+        history.push(expr, &result);
+        history.save()?;
+        copy(&result);
+        Ok(result)
+    }
+}
+
+fn eval(
+    expr: &str,
+    rates: currency::Rates,
+    history: &History,
+    variables: &Variables,
+    clock: time::Clock,
+) -> Result<QueryReply, EvalError> {
+    // Definition errors are reported by the header and `--check-defs`, not on every evaluation
+    let (mut ctx, _) = context(rates, variables, &units::user_files());
+    eval_with(&mut ctx, expr, history, clock)
+}
+
+/// Evaluates `expr` with an existing context
+fn eval_with(
+    ctx: &mut rink_core::Context,
+    expr: &str,
+    history: &History,
+    clock: time::Clock,
+) -> Result<QueryReply, EvalError> {
+    // An assignment evaluates to the value of its definition
+    let rhs = history::split_assignment(expr).map_or(expr, |(_, def)| def);
+    // The trimmed definition ends where the trimmed expression does
+    let offset = expr[..expr.trim_end().len() - rhs.trim_end().len()]
+        .chars()
+        .count();
+    let query = history::substitute_with(rhs, |_, n| history.get(n).map(String::from));
+    time::eval(ctx, &query.query, clock).map_err(|e| EvalError::new(expr, &e, &query, offset))
+}
+
+/// Builds a rink context with the bundled, user and variable definitions.
+/// Also returns the errors found in the user definition files.
+fn context(
+    rates: currency::Rates,
+    variables: &Variables,
+    files: &[PathBuf],
+) -> (rink_core::Context, Vec<units::DefError>) {
+    let mut ctx = rink_core::Context::new();
+
+    if let Some(f) = DATES_FILE {
+        ctx.load_dates(datetime::parse_datefile(f));
+    }
+
+    let _ = ctx.load(rates.defs);
+
+    if let Some(f) = DEFAULT_FILE {
+        let _ = ctx.load(gnu_units::parse_str(f));
+    }
+
+    let errors = units::load_files(&mut ctx, files);
+
+    let _ = ctx.load(gnu_units::parse_str(&variables.to_units()));
+
+    (ctx, errors)
+}
+
+/// Validates the user definition files against the same rates as the evaluations,
+/// printing every error. Returns true if they all loaded cleanly.
+fn check_defs(rates: currency::Rates, files: &[PathBuf]) -> bool {
+    let (_, errors) = context(rates, &Variables::default(), files);
+    for e in &errors {
+        eprintln!("{e}");
+    }
+    if files.is_empty() {
+        println!(
+            "No definition files found in {}",
+            units::units_dir().display()
+        );
+    } else if errors.is_empty() {
+        println!("{} definition file(s) OK", files.len());
+    }
+    errors.is_empty()
+}
+
+/// Copies `text` to the Wayland clipboard
+pub(crate) fn copy(text: &str) {
+    let _ = Command::new("wl-copy")
+        .arg(text)
+        .spawn()
+        .and_then(|mut h| h.wait());
+}
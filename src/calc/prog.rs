@@ -0,0 +1,787 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt::{Display, Write as _},
+    rc::Rc,
+    sync::{Arc, atomic::AtomicUsize},
+};
+
+use anyhow::Context as _;
+use clap::ValueEnum as _;
+use skim::{
+    ItemPreview, PreviewContext, SkimItem, SkimOutput,
+    prelude::{Sender, SkimItemReceiver, bounded, unbounded},
+    reader::CommandCollector,
+};
+
+use crate::{Mode, SkimRun};
+
+use super::{EvalError, history, history::History};
+
+/// Fixed-width integer types, arithmetic wraps like Rust's `wrapping_*` methods
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum IntType {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    #[default]
+    I64,
+    I128,
+}
+
+impl IntType {
+    #[must_use]
+    pub fn bits(self) -> u32 {
+        match self {
+            IntType::U8 | IntType::I8 => 8,
+            IntType::U16 | IntType::I16 => 16,
+            IntType::U32 | IntType::I32 => 32,
+            IntType::U64 | IntType::I64 => 64,
+            IntType::U128 | IntType::I128 => 128,
+        }
+    }
+
+    #[must_use]
+    pub fn signed(self) -> bool {
+        matches!(
+            self,
+            IntType::I8 | IntType::I16 | IntType::I32 | IntType::I64 | IntType::I128
+        )
+    }
+
+    /// The type with the same width and the other signedness
+    fn flipped(self) -> Self {
+        match self {
+            IntType::U8 => IntType::I8,
+            IntType::U16 => IntType::I16,
+            IntType::U32 => IntType::I32,
+            IntType::U64 => IntType::I64,
+            IntType::U128 => IntType::I128,
+            IntType::I8 => IntType::U8,
+            IntType::I16 => IntType::U16,
+            IntType::I32 => IntType::U32,
+            IntType::I64 => IntType::U64,
+            IntType::I128 => IntType::U128,
+        }
+    }
+
+    fn mask(self) -> u128 {
+        u128::MAX >> (128 - self.bits())
+    }
+}
+
+impl Display for IntType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default();
+        write!(f, "{name}")
+    }
+}
+
+/// An integer of a given type, stored as its two's complement bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Int {
+    bits: u128,
+    ty: IntType,
+}
+
+impl Int {
+    fn new(bits: u128, ty: IntType) -> Self {
+        Self {
+            bits: bits & ty.mask(),
+            ty,
+        }
+    }
+
+    /// The value sign-extended to 128 bits
+    #[allow(clippy::cast_possible_wrap)]
+    fn to_i128(self) -> i128 {
+        let shift = 128 - self.ty.bits();
+        ((self.bits << shift) as i128) >> shift
+    }
+
+    /// Converts to `ty`, truncating or extending like Rust's `as`
+    #[allow(clippy::cast_sign_loss)]
+    fn cast(self, ty: IntType) -> Self {
+        let wide = if self.ty.signed() {
+            self.to_i128() as u128
+        } else {
+            self.bits
+        };
+        Int::new(wide, ty)
+    }
+
+    fn is_zero(self) -> bool {
+        self.bits == 0
+    }
+
+    /// The same bits read with the other signedness
+    fn reinterpreted(self) -> Self {
+        Int::new(self.bits, self.ty.flipped())
+    }
+
+    #[must_use]
+    pub fn hex(self) -> String {
+        format!("0x{:x}", self.bits)
+    }
+
+    #[must_use]
+    pub fn oct(self) -> String {
+        format!("0o{:o}", self.bits)
+    }
+
+    #[must_use]
+    pub fn bin(self) -> String {
+        format!("0b{:b}", self.bits)
+    }
+
+    /// The bits in rows of 32, grouped by nibble, each row starting with its highest bit index
+    #[must_use]
+    pub fn layout(self) -> String {
+        let bits = self.ty.bits();
+        let row_len = bits.min(32);
+        let mut out = String::new();
+        for row in (0..bits / row_len).rev() {
+            let high = (row + 1) * row_len - 1;
+            let _ = write!(out, "{high:>3} ");
+            for bit in (row * row_len..=high).rev() {
+                out.push(if self.bits >> bit & 1 == 1 { '1' } else { '0' });
+                if bit % 4 == 0 && bit != row * row_len {
+                    out.push(' ');
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl Display for Int {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ty.signed() {
+            write!(f, "{}", self.to_i128())
+        } else {
+            write!(f, "{}", self.bits)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(u128),
+    Ident(String),
+    Op(&'static str),
+}
+
+/// Operators, longest first so `<<` wins over `<`
+static OPS: &[&str] = &[
+    "**", "<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~", "!", "(", ")",
+];
+
+/// A parse or evaluation error at a char column of the query
+struct ProgError {
+    message: String,
+    position: usize,
+}
+
+fn error<T>(message: impl Into<String>, position: usize) -> Result<T, ProgError> {
+    Err(ProgError {
+        message: message.into(),
+        position,
+    })
+}
+
+/// Splits `expr` into tokens, with their char column
+fn tokenize(expr: &str) -> Result<Vec<(Token, usize)>, ProgError> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+            tokens.push((Token::Num(parse_literal(&literal, start)?), start));
+        } else if c.is_alphabetic() {
+            while i < chars.len() && chars[i].is_alphanumeric() {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), start));
+        } else if let Some(op) = OPS.iter().find(|op| {
+            op.chars()
+                .enumerate()
+                .all(|(j, o)| chars.get(i + j) == Some(&o))
+        }) {
+            i += op.len();
+            tokens.push((Token::Op(op), start));
+        } else {
+            return error(format!("Unexpected character `{c}`"), start);
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses a decimal, `0x`, `0o` or `0b` literal
+fn parse_literal(literal: &str, position: usize) -> Result<u128, ProgError> {
+    let (digits, radix) = match literal.get(..2) {
+        Some("0x" | "0X") => (&literal[2..], 16),
+        Some("0o" | "0O") => (&literal[2..], 8),
+        Some("0b" | "0B") => (&literal[2..], 2),
+        _ => (literal, 10),
+    };
+    u128::from_str_radix(digits, radix).or_else(|e| {
+        let reason = match e.kind() {
+            std::num::IntErrorKind::PosOverflow => "does not fit in 128 bits",
+            _ => "is not a valid number",
+        };
+        error(format!("Literal `{literal}` {reason}"), position)
+    })
+}
+
+/// Recursive descent evaluator, operators bind like in Rust with `**` for powers
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// Type of the literals
+    ty: IntType,
+    /// Column just past the expression, for errors at its end
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, c)| *c)
+    }
+
+    fn next_op(&mut self, ops: &[&str]) -> Option<(&'static str, usize)> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Op(op), column)) if ops.contains(op) => {
+                let found = (*op, *column);
+                self.pos += 1;
+                Some(found)
+            }
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Int, ProgError> {
+        self.binary(0)
+    }
+
+    /// Binary operators by increasing precedence
+    fn binary(&mut self, level: usize) -> Result<Int, ProgError> {
+        static LEVELS: &[&[&str]] = &[
+            &["|"],
+            &["^"],
+            &["&"],
+            &["<<", ">>"],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        let Some(ops) = LEVELS.get(level) else {
+            return self.cast();
+        };
+        let mut lhs = self.binary(level + 1)?;
+        while let Some((op, column)) = self.next_op(ops) {
+            let rhs = self.binary(level + 1)?;
+            lhs = apply(op, lhs, rhs).or_else(|m| error(m, column))?;
+        }
+        Ok(lhs)
+    }
+
+    /// `x as u8`
+    fn cast(&mut self) -> Result<Int, ProgError> {
+        let mut value = self.unary()?;
+        while matches!(self.peek(), Some(Token::Ident(i)) if i == "as") {
+            self.pos += 1;
+            let column = self.column();
+            let Some(Token::Ident(name)) = self.peek() else {
+                return error("Expected a type after `as`", column);
+            };
+            let Ok(ty) = IntType::from_str(name, true) else {
+                return error(format!("Unknown type `{name}`"), column);
+            };
+            self.pos += 1;
+            value = value.cast(ty);
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<Int, ProgError> {
+        match self.next_op(&["-", "~", "!"]) {
+            Some(("-", _)) => {
+                let value = self.unary()?;
+                Ok(Int::new(value.bits.wrapping_neg(), value.ty))
+            }
+            Some(_) => {
+                let value = self.unary()?;
+                Ok(Int::new(!value.bits, value.ty))
+            }
+            None => self.power(),
+        }
+    }
+
+    /// `**` is right associative and binds tighter than unary minus on its left
+    fn power(&mut self) -> Result<Int, ProgError> {
+        let base = self.atom()?;
+        if let Some((_, column)) = self.next_op(&["**"]) {
+            let exp = self.unary()?;
+            return pow(base, exp).or_else(|m| error(m, column));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Int, ProgError> {
+        let column = self.column();
+        let Some((token, _)) = self.tokens.get(self.pos).cloned() else {
+            return error("Expected a number, got end of input", column);
+        };
+        self.pos += 1;
+        match token {
+            Token::Num(n) => Ok(Int::new(n, self.ty)),
+            Token::Op("(") => {
+                let value = self.expr()?;
+                if self.next_op(&[")"]).is_none() {
+                    return error("Expected `)`", self.column());
+                }
+                Ok(value)
+            }
+            Token::Op(op) => error(format!("Expected a number, got `{op}`"), column),
+            Token::Ident(i) => error(format!("Unknown name `{i}`"), column),
+        }
+    }
+}
+
+/// Applies a binary operator, the result has the type of `lhs`
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn apply(op: &str, lhs: Int, rhs: Int) -> Result<Int, String> {
+    let ty = lhs.ty;
+    let rhs = rhs.cast(ty);
+    // Shift amounts wrap at the width, like `wrapping_shl`
+    let shift = (rhs.bits as u32) & (ty.bits() - 1);
+    let bits = match op {
+        "+" => lhs.bits.wrapping_add(rhs.bits),
+        "-" => lhs.bits.wrapping_sub(rhs.bits),
+        "*" => lhs.bits.wrapping_mul(rhs.bits),
+        "/" | "%" if rhs.is_zero() => return Err(String::from("Division by zero")),
+        "/" if ty.signed() => lhs.to_i128().wrapping_div(rhs.to_i128()) as u128,
+        "%" if ty.signed() => lhs.to_i128().wrapping_rem(rhs.to_i128()) as u128,
+        "/" => lhs.bits / rhs.bits,
+        "%" => lhs.bits % rhs.bits,
+        "&" => lhs.bits & rhs.bits,
+        "|" => lhs.bits | rhs.bits,
+        "^" => lhs.bits ^ rhs.bits,
+        "<<" => lhs.bits << shift,
+        ">>" if ty.signed() => (lhs.to_i128() >> shift) as u128,
+        ">>" => lhs.bits >> shift,
+        _ => return Err(format!("Unknown operator `{op}`")),
+    };
+    Ok(Int::new(bits, ty))
+}
+
+fn pow(base: Int, exp: Int) -> Result<Int, String> {
+    if exp.ty.signed() && exp.to_i128() < 0 {
+        return Err(String::from("Negative exponent"));
+    }
+    let exp = u32::try_from(exp.bits).unwrap_or(u32::MAX);
+    Ok(Int::new(base.bits.wrapping_pow(exp), base.ty))
+}
+
+/// Evaluates `expr` with literals of type `ty`, `_` and `_N` refer to the history
+///
+/// # Errors
+/// Returns an error pointing at the offending token if `expr` is invalid.
+pub fn eval(expr: &str, ty: IntType, history: &History) -> Result<Int, EvalError> {
+    // The history is shared with `calc`, whose results like `3.5 meter` are not integers
+    let mut not_int = None;
+    let query = history::substitute_with(expr, |column, n| {
+        let result = history.get(n)?;
+        if not_int.is_none() && parse(result, ty).is_err() {
+            not_int = Some(ProgError {
+                message: format!("History result `{result}` is not an integer"),
+                position: column,
+            });
+        }
+        Some(result.to_string())
+    });
+    let to_eval_error = |e: ProgError| EvalError {
+        expr: expr.to_string(),
+        message: e.message,
+        position: Some(query.column(e.position)),
+    };
+    if let Some(e) = not_int {
+        // Reported at the reference, a column of `expr` itself
+        return Err(EvalError {
+            expr: expr.to_string(),
+            message: e.message,
+            position: Some(e.position),
+        });
+    }
+    parse(&query.query, ty).map_err(to_eval_error)
+}
+
+/// Evaluates `query`, which must be fully consumed
+fn parse(query: &str, ty: IntType) -> Result<Int, ProgError> {
+    let tokens = tokenize(query)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        ty,
+        end: query.trim_end().chars().count(),
+    };
+    let value = parser.expr()?;
+    if let Some(token) = parser.peek() {
+        let got = match token {
+            Token::Num(n) => n.to_string(),
+            Token::Ident(i) => i.clone(),
+            Token::Op(op) => (*op).to_string(),
+        };
+        return error(
+            format!("Expected an operator, got `{got}`"),
+            parser.column(),
+        );
+    }
+    Ok(value)
+}
+
+/// A representation of a result, the preview shows its bit layout
+struct ProgItem {
+    label: String,
+    value: String,
+    int: Int,
+}
+
+impl ProgItem {
+    fn all(int: Int) -> Vec<Self> {
+        let other = int.reinterpreted();
+        let mut reprs = vec![
+            (int.ty.to_string(), int.to_string()),
+            (String::from("hex"), int.hex()),
+            (String::from("oct"), int.oct()),
+            (String::from("bin"), int.bin()),
+        ];
+        // Two's complement: the same bits read with the other signedness
+        if other.to_string() != int.to_string() {
+            reprs.push((other.ty.to_string(), other.to_string()));
+        }
+        reprs
+            .into_iter()
+            .map(|(label, value)| ProgItem { label, value, int })
+            .collect()
+    }
+}
+
+impl SkimItem for ProgItem {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Owned(format!("{}: {}", self.label, self.value))
+    }
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.value)
+    }
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        let int = self.int;
+        ItemPreview::Text(format!(
+            "{} = {} ({})\n\n{}\nones: {} | leading zeros: {} | trailing zeros: {}",
+            int.ty,
+            int,
+            int.hex(),
+            int.layout(),
+            int.bits.count_ones(),
+            int.bits.leading_zeros() - (128 - int.ty.bits()),
+            int.bits.trailing_zeros().min(int.ty.bits()),
+        ))
+    }
+}
+
+/// Evaluates the interactive queries in-process
+struct ProgEvaluator {
+    ty: IntType,
+    history: History,
+}
+
+impl CommandCollector for ProgEvaluator {
+    fn invoke(
+        &mut self,
+        cmd: &str,
+        _components_to_stop: Arc<AtomicUsize>,
+    ) -> (SkimItemReceiver, Sender<i32>) {
+        let (tx_item, rx_item) = unbounded();
+        // Evaluation is synchronous, there is nothing to interrupt
+        let (tx_interrupt, _) = bounded(1);
+        if !cmd.trim().is_empty() {
+            match eval(cmd, self.ty, &self.history) {
+                Ok(int) => {
+                    for item in ProgItem::all(int) {
+                        let _ = tx_item.send(Arc::new(item) as Arc<dyn SkimItem>);
+                    }
+                }
+                Err(e) => {
+                    let _ = tx_item.send(Arc::new(e.pretty()) as Arc<dyn SkimItem>);
+                }
+            }
+        }
+        (rx_item, tx_interrupt)
+    }
+}
+
+/// Programmer calculator: fixed-width integers, bitwise operators and base literals.
+/// Shares the history, `_` references and clipboard handling of `calc`.
+#[derive(Default)]
+pub struct Prog {
+    pub ty: IntType,
+}
+
+impl Prog {
+    /// Evaluates `expr`, stores it in the history and copies the result.
+    /// `pick` is one of the representations, copied instead of the decimal value.
+    pub(crate) fn accept(&self, expr: &str, pick: Option<&str>) -> anyhow::Result<String> {
+        let mut history = History::load();
+        let int = eval(expr, self.ty, &history)?;
+        let result = pick.map_or_else(|| int.to_string(), String::from);
+        history.push(expr, &result);
+        history.save()?;
+        super::copy(&result);
+        Ok(result)
+    }
+}
+
+impl SkimRun for Prog {
+    fn get(&self) -> Vec<Arc<dyn SkimItem>> {
+        vec![]
+    }
+    fn set_options(&self, opts: &mut skim::SkimOptions) {
+        let exe = crate::current_exe();
+        let ty = self.ty;
+        let history = History::load();
+        opts.header = Some(format!(
+            "calc-prog ({ty}) - previous(_): {} | ops: & | ^ ~ << >> ** | cast: as u8",
+            history.get(0).unwrap_or("N/A"),
+        ));
+        opts.cmd = Some(String::from("{}"));
+        opts.cmd_collector = Rc::new(RefCell::new(ProgEvaluator { ty, history }));
+        opts.interactive = true;
+        opts.preview = Some(String::new());
+        opts.preview_window = String::from("up:40%");
+        // Save the selected representation to the history, then start over
+        opts.bind.extend(vec![format!(
+            "enter:execute-silent({exe} calc-prog --type {ty} --eval --save --pick {{}} {{cq}})+accept(calc-prog --type {ty})"
+        )]);
+    }
+    fn run(&self, output: &SkimOutput) -> anyhow::Result<()> {
+        let pick = output
+            .selected_items
+            .first()
+            .map(|i| i.output().to_string());
+        println!("{}", self.accept(&output.cmd, pick.as_deref())?);
+        Ok(())
+    }
+    fn init(&self, mode: &Mode) -> bool {
+        let Mode::CalcProg {
+            eval: true,
+            save,
+            pick,
+            expr,
+            ..
+        } = mode
+        else {
+            return true;
+        };
+        let expr = expr.join(" ");
+        if expr.trim().is_empty() {
+            return false;
+        }
+        if *save {
+            match self
+                .accept(&expr, pick.as_deref())
+                .context("Evaluation failed")
+            {
+                Ok(result) => println!("{result}"),
+                Err(e) => {
+                    eprintln!("{e:#}");
+                    std::process::exit(1);
+                }
+            }
+            return false;
+        }
+        match eval(&expr, self.ty, &History::load()) {
+            Ok(int) => {
+                for item in ProgItem::all(int) {
+                    println!("{}", item.text());
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e.pretty());
+                std::process::exit(1);
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::history::Entry;
+
+    fn eval_as(expr: &str, ty: IntType) -> String {
+        match eval(expr, ty, &History::default()) {
+            Ok(int) => int.to_string(),
+            Err(e) => e.pretty(),
+        }
+    }
+
+    fn eval_i64(expr: &str) -> String {
+        eval_as(expr, IntType::I64)
+    }
+
+    fn history(results: &[&str]) -> History {
+        History {
+            entries: results
+                .iter()
+                .map(|r| Entry {
+                    expr: String::new(),
+                    result: (*r).to_string(),
+                    timestamp: 0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval_i64("1 + 2 * 3"), "7");
+        assert_eq!(eval_i64("(1 + 2) * 3"), "9");
+        assert_eq!(eval_i64("10 - 4 - 3"), "3");
+        assert_eq!(eval_i64("1 | 2 ^ 3 & 6"), "1");
+        assert_eq!(eval_i64("1 + 1 << 2"), "8");
+        assert_eq!(eval_i64("2 ** 3 ** 2"), "512");
+        assert_eq!(eval_i64("-2 ** 2"), "-4");
+        assert_eq!(eval_i64("2 * -3"), "-6");
+        assert_eq!(eval_i64("~0"), "-1");
+        assert_eq!(eval_i64("7 % 4 * 2"), "6");
+    }
+
+    #[test]
+    fn shifts() {
+        assert_eq!(eval_i64("1 << 4"), "16");
+        assert_eq!(eval_i64("256 >> 4"), "16");
+        // Arithmetic shift for signed types, logical for unsigned ones
+        assert_eq!(eval_i64("-16 >> 2"), "-4");
+        assert_eq!(eval_as("0xf0 >> 4", IntType::U8), "15");
+        assert_eq!(eval_as("-16 >> 2", IntType::U8), "60");
+        // Shift amounts wrap at the width
+        assert_eq!(eval_as("1 << 9", IntType::U8), "2");
+        assert_eq!(eval_as("1 << 8", IntType::I8), "1");
+    }
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(eval_as("255 + 1", IntType::U8), "0");
+        assert_eq!(eval_as("0 - 1", IntType::U8), "255");
+        assert_eq!(eval_as("127 + 1", IntType::I8), "-128");
+        assert_eq!(eval_as("-128 / -1", IntType::I8), "-128");
+        assert_eq!(eval_as("0xffff * 2", IntType::U16), "65534");
+        assert_eq!(eval_as("2 ** 32", IntType::U32), "0");
+        assert_eq!(eval_i64("9223372036854775807 + 1"), "-9223372036854775808");
+        assert_eq!(eval_as("0 - 1", IntType::U128), u128::MAX.to_string());
+        assert_eq!(eval_as("-7 / 2", IntType::I32), "-3");
+        assert_eq!(eval_as("-7 % 2", IntType::I32), "-1");
+    }
+
+    #[test]
+    fn casts() {
+        assert_eq!(eval_i64("300 as u8"), "44");
+        assert_eq!(eval_i64("-1 as u16"), "65535");
+        assert_eq!(eval_as("255 as i8", IntType::U8), "-1");
+        assert_eq!(eval_as("200 as i8 as i32", IntType::U8), "-56");
+        assert_eq!(eval_as("200 as u32", IntType::U8), "200");
+        // The left operand gives its type to the result
+        assert_eq!(eval_i64("(255 as u8) + 1"), "0");
+        assert_eq!(eval_i64("1 as i4"), "1 as ‸i4 - Unknown type `i4`");
+        assert_eq!(eval_i64("1 as"), "1 as‸ - Expected a type after `as`");
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(eval_i64("0xff"), "255");
+        assert_eq!(eval_i64("0XFF"), "255");
+        assert_eq!(eval_i64("0o17"), "15");
+        assert_eq!(eval_i64("0b1111_0000"), "240");
+        assert_eq!(eval_i64("1_000_000"), "1000000");
+        assert_eq!(eval_as("0xff", IntType::I8), "-1");
+        assert_eq!(
+            eval_i64("0b102"),
+            "‸0b102 - Literal `0b102` is not a valid number"
+        );
+        assert_eq!(
+            eval_i64("0x1_0000_0000_0000_0000_0000_0000_0000_0000"),
+            "‸0x1_0000_0000_0000_0000_0000_0000_0000_0000 - Literal `0x100000000000000000000000000000000` does not fit in 128 bits"
+        );
+    }
+
+    #[test]
+    fn representations() {
+        let int = eval("-1", IntType::I8, &History::default()).unwrap();
+        assert_eq!(int.hex(), "0xff");
+        assert_eq!(int.oct(), "0o377");
+        assert_eq!(int.bin(), "0b11111111");
+        assert_eq!(int.reinterpreted().to_string(), "255");
+        assert_eq!(int.layout(), "  7 1111 1111\n");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(eval_i64("1 + * 2"), "1 + ‸* 2 - Expected a number, got `*`");
+        assert_eq!(
+            eval_i64("1 +"),
+            "1 +‸ - Expected a number, got end of input"
+        );
+        assert_eq!(eval_i64("(1 + 2"), "(1 + 2‸ - Expected `)`");
+        assert_eq!(eval_i64("1 2"), "1 ‸2 - Expected an operator, got `2`");
+        assert_eq!(eval_i64("1 / 0"), "1 ‸/ 0 - Division by zero");
+        assert_eq!(eval_i64("2 ** -1"), "2 ‸** -1 - Negative exponent");
+        assert_eq!(eval_i64("1 $ 2"), "1 ‸$ 2 - Unexpected character `$`");
+        assert_eq!(eval_i64("x + 1"), "‸x + 1 - Unknown name `x`");
+    }
+
+    #[test]
+    fn history_references() {
+        let history = history(&["0xff", "3.5 meter", "-2"]);
+        let eval = |expr| eval(expr, IntType::I64, &history).map_err(|e| e.pretty());
+        assert_eq!(
+            eval("_ + 1").map(|i| i.to_string()),
+            Ok(String::from("256"))
+        );
+        assert_eq!(
+            eval("_ * _2").map(|i| i.to_string()),
+            Ok(String::from("-510"))
+        );
+        assert_eq!(
+            eval("1 + _1").map(|i| i.to_string()),
+            Err(String::from(
+                "1 + ‸_1 - History result `3.5 meter` is not an integer"
+            ))
+        );
+        // Columns of the substituted query are mapped back to the expression
+        assert_eq!(
+            eval("_ +").map(|i| i.to_string()),
+            Err(String::from("_ +‸ - Expected a number, got end of input"))
+        );
+    }
+}
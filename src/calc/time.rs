@@ -0,0 +1,419 @@
+use std::fmt::Write as _;
+
+use chrono::{DateTime, Duration, FixedOffset, Local};
+use chrono_tz::{TZ_VARIANTS, Tz};
+use rink_core::{
+    Context,
+    output::{QueryError, QueryReply},
+    parsing::text_query,
+};
+
+/// Zones of the world clock shown for date results
+pub static DEFAULT_TIMEZONES: &[&str] = &["UTC", "America/New_York", "Europe/London", "Asia/Tokyo"];
+
+/// Source of the current time, fixed with `--now` for reproducible results
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Clock {
+    #[default]
+    System,
+    Fixed(DateTime<FixedOffset>),
+}
+
+impl Clock {
+    #[must_use]
+    pub fn now(self) -> DateTime<FixedOffset> {
+        match self {
+            Clock::System => Local::now().fixed_offset(),
+            Clock::Fixed(now) => now,
+        }
+    }
+
+    /// The flags selecting this clock, to forward them to a new process
+    #[must_use]
+    pub fn to_args(self) -> Vec<String> {
+        match self {
+            Clock::System => Vec::new(),
+            Clock::Fixed(now) => vec![String::from("--now"), now.to_rfc3339()],
+        }
+    }
+}
+
+/// Parses the `--now` flag
+///
+/// # Errors
+/// Returns an error if `s` is not an RFC 3339 date.
+pub fn parse_clock(s: &str) -> Result<Clock, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(Clock::Fixed)
+        .map_err(|e| format!("{e}, expected a date like 2026-01-31T12:00:00+01:00"))
+}
+
+/// Finds a timezone by its full name (`Asia/Tokyo`) or its city (`Tokyo`, `new york`)
+///
+/// # Errors
+/// Returns an error if no timezone matches.
+pub fn parse_zone(name: &str) -> Result<Tz, String> {
+    let wanted = name.trim().replace(' ', "_");
+    TZ_VARIANTS
+        .iter()
+        .find(|tz| tz.name().eq_ignore_ascii_case(&wanted))
+        .or_else(|| {
+            TZ_VARIANTS.iter().find(|tz| {
+                tz.name()
+                    .rsplit('/')
+                    .next()
+                    .is_some_and(|city| city.eq_ignore_ascii_case(&wanted))
+            })
+        })
+        .copied()
+        .ok_or_else(|| format!("Unknown timezone {name}"))
+}
+
+/// What a time query asks for, once the shortcuts are rewritten into rink syntax
+#[derive(Debug, PartialEq, Eq)]
+pub enum TimeQuery {
+    /// A plain rink query
+    Rink(String),
+    /// The unix timestamp of a date, `<date> to unix`
+    Unix(String),
+}
+
+/// Rewrites the time shortcuts rink does not know about:
+/// bare ISO dates, `today`/`tomorrow`/`yesterday`, `@<unix timestamp>`,
+/// `in <city>` and `to unix`.
+#[must_use]
+pub fn rewrite(query: &str, now: DateTime<FixedOffset>) -> TimeQuery {
+    let query = rewrite_tokens(query, now);
+    match split_target(&query) {
+        Some((lhs, target)) if target.eq_ignore_ascii_case("unix") => {
+            TimeQuery::Unix(lhs.to_string())
+        }
+        Some((lhs, target)) => match parse_zone(target) {
+            Ok(tz) => TimeQuery::Rink(format!("{lhs} -> \"{}\"", tz.name())),
+            Err(_) => TimeQuery::Rink(query),
+        },
+        None => TimeQuery::Rink(query),
+    }
+}
+
+/// Splits `<expr> in|to|-> <target>` on the last conversion keyword
+fn split_target(query: &str) -> Option<(&str, &str)> {
+    [" in ", " to ", "->"]
+        .iter()
+        .filter_map(|sep| query.rfind(sep).map(|i| (i, sep.len())))
+        .max_by_key(|(i, _)| *i)
+        .map(|(i, len)| (query[..i].trim(), query[i + len..].trim()))
+        .filter(|(lhs, target)| !lhs.is_empty() && !target.is_empty() && !target.contains('"'))
+}
+
+/// Wraps bare dates in `#…#` and expands the relative day names and unix timestamps
+fn rewrite_tokens(query: &str, now: DateTime<FixedOffset>) -> String {
+    let chars: Vec<char> = query.chars().collect();
+    let mut out = String::with_capacity(query.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let boundary = i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+        // Dates and strings are left to rink
+        if c == '#' || c == '"' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|d| *d == c)
+                .map_or(chars.len(), |p| i + 2 + p);
+            out.extend(&chars[i..end]);
+            i = end;
+        } else if boundary && let Some(len) = date_len(&chars[i..]) {
+            let date: String = chars[i..i + len].iter().collect();
+            // Rink wants the offset after a space, and has no `Z`
+            let date = match date.strip_suffix('Z') {
+                Some(date) => format!("{date} +00:00"),
+                // Only dates with an offset are that long
+                None if len >= 22 && chars[i + len - 7].is_ascii_digit() => {
+                    format!("{} {}", &date[..len - 6], &date[len - 6..])
+                }
+                None => date,
+            };
+            let _ = write!(out, "#{date}#");
+            i += len;
+        } else if boundary && c == '@' && chars.get(i + 1).is_some_and(char::is_ascii_digit) {
+            let digits: String = chars[i + 1..]
+                .iter()
+                .take_while(|d| d.is_ascii_digit())
+                .collect();
+            match digits
+                .parse()
+                .ok()
+                .and_then(|ts| DateTime::from_timestamp(ts, 0))
+            {
+                Some(date) => out.push_str(&rink_date(date.fixed_offset())),
+                None => {
+                    let _ = write!(out, "@{digits}");
+                }
+            }
+            i += 1 + digits.len();
+        } else if boundary && c.is_alphabetic() {
+            let word: String = chars[i..]
+                .iter()
+                .take_while(|d| d.is_alphanumeric() || **d == '_')
+                .collect();
+            let offset = match word.as_str() {
+                "today" => Some(0),
+                "tomorrow" => Some(1),
+                "yesterday" => Some(-1),
+                _ => None,
+            };
+            match offset {
+                Some(days) => {
+                    let day = (now + Duration::days(days)).date_naive();
+                    let _ = write!(out, "#{day}#");
+                }
+                None => out.push_str(&word),
+            }
+            i += word.chars().count();
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Length of the ISO date (`2026-12-01`, optionally followed by `T10:00[:00]` or ` 10:00[:00]`,
+/// then by `Z` or an offset like `+09:00` or ` +09:00`) at the start of `chars`
+fn date_len(chars: &[char]) -> Option<usize> {
+    let matches = |pattern: &str, at: usize| {
+        pattern.chars().enumerate().all(|(j, p)| {
+            chars.get(at + j).is_some_and(|c| match p {
+                'd' => c.is_ascii_digit(),
+                p => *c == p,
+            })
+        })
+    };
+    if !matches("dddd-dd-dd", 0) {
+        return None;
+    }
+    let mut len = 10;
+    if matches("Tdd:dd", len) || matches(" dd:dd", len) {
+        len += 6;
+        if matches(":dd", len) {
+            len += 3;
+        }
+        if matches("Z", len) {
+            len += 1;
+        } else if let Some(sign) = ["+dd:dd", "-dd:dd", " +dd:dd", " -dd:dd"]
+            .iter()
+            .find(|offset| matches(offset, len))
+        {
+            len += sign.len();
+        }
+    }
+    // `2026-12-012` is arithmetic, not a date
+    (!chars.get(len).is_some_and(char::is_ascii_digit)).then_some(len)
+}
+
+/// A date literal rink parses back to the same instant
+fn rink_date(date: DateTime<FixedOffset>) -> String {
+    format!("#{}#", date.format("%Y-%m-%dT%H:%M:%S %:z"))
+}
+
+/// Evaluates a rink query at the time given by `clock`.
+/// `rink_core::eval` always resets the context to the system time.
+///
+/// # Errors
+/// Returns rink's error if the query fails.
+pub fn eval(ctx: &mut Context, query: &str, clock: Clock) -> Result<QueryReply, QueryError> {
+    let now = clock.now();
+    ctx.set_time(now.with_timezone(&Local));
+    match rewrite(query, now) {
+        TimeQuery::Rink(query) => eval_rink(ctx, &query),
+        TimeQuery::Unix(date) => match eval_rink(ctx, &date)? {
+            QueryReply::Date(reply) => {
+                let date = DateTime::parse_from_rfc3339(&reply.rfc3339)
+                    .map_err(|e| QueryError::generic(e.to_string()))?;
+                let timestamp = date.timestamp().to_string();
+                let mut reply = eval_rink(ctx, &timestamp)?;
+                // Rink shows large integers in scientific notation
+                if let QueryReply::Number(parts) = &mut reply {
+                    parts.exact_value = Some(timestamp);
+                    parts.approx_value = None;
+                }
+                Ok(reply)
+            }
+            _ => Err(QueryError::generic(format!(
+                "Expected a date before `to unix`, got {date}"
+            ))),
+        },
+    }
+}
+
+fn eval_rink(ctx: &Context, query: &str) -> Result<QueryReply, QueryError> {
+    let mut iter = text_query::TokenIterator::new(query.trim()).peekable();
+    let query = text_query::parse_query(&mut iter);
+    ctx.eval_query(&query)
+}
+
+/// A date result in each of `zones`, as `(zone, date)`
+#[must_use]
+pub fn world_clock(rfc3339: &str, zones: &[Tz]) -> Vec<(&'static str, String)> {
+    let Ok(date) = DateTime::parse_from_rfc3339(rfc3339) else {
+        return Vec::new();
+    };
+    let mut clock = vec![(
+        "local",
+        date.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string(),
+    )];
+    clock.extend(zones.iter().map(|tz| {
+        (
+            tz.name(),
+            date.with_timezone(tz)
+                .format("%Y-%m-%d %H:%M:%S %Z")
+                .to_string(),
+        )
+    }));
+    clock.push(("unix", date.timestamp().to_string()));
+    clock
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::format::OutputFormat;
+
+    fn clock() -> Clock {
+        parse_clock("2026-10-19T09:30:00+02:00").unwrap()
+    }
+
+    fn eval_fixed(query: &str) -> String {
+        let mut ctx = rink_core::simple_context().unwrap();
+        match eval(&mut ctx, query, clock()) {
+            Ok(reply) => OutputFormat::default().display(&reply),
+            Err(e) => format!("error: {e}"),
+        }
+    }
+
+    fn rink(query: &str) -> TimeQuery {
+        TimeQuery::Rink(query.to_string())
+    }
+
+    #[test]
+    fn now_in_city() {
+        assert_eq!(eval_fixed("now in Tokyo"), "2026-10-19 16:30:00 JST (now)");
+        assert_eq!(
+            eval_fixed("now to new york"),
+            "2026-10-19 03:30:00 EDT (now)"
+        );
+        assert_eq!(
+            eval_fixed("now -> Europe/London"),
+            "2026-10-19 08:30:00 BST (now)"
+        );
+        assert_eq!(
+            eval_fixed("2026-12-01 10:00 in tokyo"),
+            "2026-12-01 19:00:00 JST (in a month)"
+        );
+    }
+
+    #[test]
+    fn relative_days() {
+        assert_eq!(
+            eval_fixed("2026-12-01 - today"),
+            "6 week, 1 day, 0 second (time)"
+        );
+        assert_eq!(eval_fixed("tomorrow - yesterday"), "2 day, 0 second (time)");
+        assert_eq!(
+            eval_fixed("#2026-01-01# - #2025-01-01# to days"),
+            "365 day (time)"
+        );
+        let now = clock().now();
+        assert_eq!(rewrite("today", now), rink("#2026-10-19#"));
+        // Only whole words are day names
+        assert_eq!(rewrite("todays", now), rink("todays"));
+    }
+
+    #[test]
+    fn unix_timestamps() {
+        assert_eq!(
+            eval_fixed("@1767225600"),
+            "2026-01-01 00:00:00 +00:00 (9 months ago)"
+        );
+        assert_eq!(
+            eval_fixed("@1767225600 to unix"),
+            "1767225600 (dimensionless)"
+        );
+        assert_eq!(
+            eval_fixed("2026-01-01T00:00:00Z to unix"),
+            "1767225600 (dimensionless)"
+        );
+        assert_eq!(
+            eval_fixed("2026-01-01T09:00+09:00 to unix"),
+            "1767225600 (dimensionless)"
+        );
+        assert_eq!(
+            eval_fixed("2026-01-01 04:00:00 +04:00 -> unix"),
+            "1767225600 (dimensionless)"
+        );
+        assert_eq!(
+            eval_fixed("3 to unix"),
+            "error: Expected a date before `to unix`, got 3"
+        );
+    }
+
+    #[test]
+    fn target_splitting() {
+        let now = clock().now();
+        assert_eq!(
+            rewrite("now in new york", now),
+            rink("now -> \"America/New_York\"")
+        );
+        assert_eq!(rewrite("now to Tokyo", now), rink("now -> \"Asia/Tokyo\""));
+        assert_eq!(rewrite("now->UTC", now), rink("now -> \"UTC\""));
+        assert_eq!(
+            rewrite("@0 to unix", now),
+            TimeQuery::Unix(String::from("#1970-01-01T00:00:00 +00:00#"))
+        );
+        // The last keyword wins, targets that are not zones are left to rink
+        assert_eq!(
+            rewrite("1 inch in cm to tokyo", now),
+            rink("1 inch in cm -> \"Asia/Tokyo\"")
+        );
+        assert_eq!(rewrite("1 km in m", now), rink("1 km in m"));
+        assert_eq!(rewrite("3 m to ft", now), rink("3 m to ft"));
+        assert_eq!(rewrite("to tokyo", now), rink("to tokyo"));
+        assert_eq!(
+            rewrite("now -> \"Asia/Tokyo\"", now),
+            rink("now -> \"Asia/Tokyo\"")
+        );
+        assert_eq!(eval_fixed("1 km in m"), "1000 meter (length)");
+    }
+
+    #[test]
+    fn dates_are_wrapped() {
+        let now = clock().now();
+        assert_eq!(rewrite("2026-12-01", now), rink("#2026-12-01#"));
+        assert_eq!(rewrite("2026-12-01T10:00", now), rink("#2026-12-01T10:00#"));
+        assert_eq!(
+            rewrite("2026-12-01T10:00:00Z", now),
+            rink("#2026-12-01T10:00:00 +00:00#")
+        );
+        // Arithmetic and existing date literals are left alone
+        assert_eq!(rewrite("2026-12-012", now), rink("2026-12-012"));
+        assert_eq!(rewrite("#2026-12-01#", now), rink("#2026-12-01#"));
+    }
+
+    #[test]
+    fn world_clock_zones() {
+        let zones = [parse_zone("UTC").unwrap(), parse_zone("tokyo").unwrap()];
+        let clock = world_clock("2026-10-19T09:30:00+02:00", &zones);
+        assert_eq!(clock[0].0, "local");
+        assert_eq!(
+            clock[1..],
+            [
+                ("UTC", String::from("2026-10-19 07:30:00 UTC")),
+                ("Asia/Tokyo", String::from("2026-10-19 16:30:00 JST")),
+                ("unix", String::from("1792395000")),
+            ]
+        );
+        assert!(parse_zone("nowhere").is_err());
+    }
+}
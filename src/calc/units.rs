@@ -0,0 +1,364 @@
+use std::{fmt::Display, fs, path::PathBuf};
+
+use rink_core::{
+    Context,
+    loader::gnu_units::{self, Iter, Token, TokenIterator},
+};
+
+use crate::xdg;
+
+static UNITS_DIR: &str = "units";
+static UNITS_EXT: &str = "units";
+
+/// An error found while loading a user definition file
+#[derive(Debug, Clone)]
+pub struct DefError {
+    pub path: PathBuf,
+    /// 1-based line of the definition, when it could be found
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Display for DefError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {}", self.path.display(), self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// Directory holding the user definition files, `$XDG_CONFIG_HOME/skim-run/units`
+#[must_use]
+pub fn units_dir() -> PathBuf {
+    xdg::config_dir().join(UNITS_DIR)
+}
+
+/// Every `*.units` file of the units dir, sorted by name so the load order is stable
+#[must_use]
+pub fn user_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(units_dir())
+        .map(|dir| {
+            dir.filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == UNITS_EXT))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Loads definition files, like the [`user_files`], in GNU units format into `ctx`.
+/// Must run after the bundled definitions since user units build on them.
+pub fn load_files(ctx: &mut Context, files: &[PathBuf]) -> Vec<DefError> {
+    let mut errors = Vec::new();
+    for path in files {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                errors.push(DefError {
+                    path: path.clone(),
+                    line: None,
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        // Lines with syntax errors are left out, rink would print their errors
+        let (content, syntax_errors) = check_syntax(&content);
+        errors.extend(syntax_errors.into_iter().map(|(line, message)| DefError {
+            path: path.clone(),
+            line: Some(line),
+            message,
+        }));
+        if let Err(e) = ctx.load(gnu_units::parse_str(&content)) {
+            errors.extend(
+                e.lines()
+                    // Skip the "Multiple errors encountered" heading
+                    .filter(|l| l.starts_with(char::is_whitespace))
+                    .map(|l| DefError {
+                        path: path.clone(),
+                        line: find_line(&content, l.trim()),
+                        message: l.trim().to_string(),
+                    }),
+            );
+        }
+    }
+    errors
+}
+
+/// Rink's parser prints syntax errors to stdout, without their file and with a line that
+/// drifts, and lets a truncated definition swallow the next line. Checks each definition
+/// on its own, returns the content with the invalid ones blanked and their 1-based lines
+/// with the errors.
+fn check_syntax(content: &str) -> (String, Vec<(usize, String)>) {
+    let lines: Vec<&str> = content.lines().collect();
+    // A definition continues on the next line after a trailing backslash
+    let mut definitions = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start + 1;
+        while end < lines.len() && lines[end - 1].trim_end().ends_with('\\') {
+            end += 1;
+        }
+        definitions.push((start, end, lines[start..end].join("\n")));
+        start = end;
+    }
+
+    let mut errors = Vec::new();
+    let mut checked = Vec::with_capacity(lines.len());
+    let mut in_category = false;
+    let mut definitions = definitions.into_iter();
+    while let Some((start, mut end, definition)) = definitions.next() {
+        let mut tokens = TokenIterator::new(&definition).peekable();
+        let mut result = check_definition(&mut tokens, &mut in_category).map_err(|e| (start, e));
+        // A substance lists its properties on the following lines, up to a `}`
+        if result == Ok(true) {
+            for (line, property_end, property) in definitions.by_ref() {
+                end = property_end;
+                match check_property(&property) {
+                    Ok(true) => break,
+                    Err(e) if result.is_ok() => result = Err((line, e)),
+                    _ => {}
+                }
+            }
+        }
+        match result {
+            Err((line, message)) => {
+                errors.push((line + 1, message));
+                checked.extend(std::iter::repeat_n("", end - start));
+            }
+            Ok(_) => checked.extend(&lines[start..end]),
+        }
+    }
+    (checked.join("\n"), errors)
+}
+
+/// Checks a definition like rink's parser, `Ok(true)` when it opens a substance
+fn check_definition(tokens: &mut Iter, in_category: &mut bool) -> Result<bool, String> {
+    match next(tokens) {
+        Token::Newline | Token::Eof | Token::Doc(_) => return Ok(false),
+        Token::Bang => match next(tokens) {
+            Token::Ident(d) if d == "category" || d == "symbol" => {
+                if !matches!(
+                    (next(tokens), next(tokens)),
+                    (Token::Ident(_), Token::Ident(_))
+                ) {
+                    return Err(format!("Malformed {d} directive"));
+                }
+                *in_category |= d == "category";
+            }
+            Token::Ident(d) if d == "endcategory" => {
+                if !*in_category {
+                    return Err("Stray endcategory directive".to_string());
+                }
+                *in_category = false;
+            }
+            Token::Ident(d) => return Err(format!("Unknown directive !{d}")),
+            x => return Err(format!("Expected directive after !, got {x:?}")),
+        },
+        Token::Ident(name) if name.ends_with('-') => check_expr(tokens)?,
+        Token::Ident(_) => match peek(tokens) {
+            Token::Bang => {
+                tokens.next();
+                if let Token::Ident(_) = peek(tokens) {
+                    tokens.next();
+                }
+            }
+            Token::Question => {
+                tokens.next();
+                check_expr(tokens)?;
+            }
+            Token::LeftBrace => {
+                tokens.next();
+                return end_of_line(tokens).map(|()| true);
+            }
+            _ => check_expr(tokens)?,
+        },
+        x => return Err(format!("Expected definition, got {x:?}")),
+    }
+    end_of_line(tokens).map(|()| false)
+}
+
+/// Checks a property line of a substance, `Ok(true)` at its closing `}`
+fn check_property(line: &str) -> Result<bool, String> {
+    let mut tokens = TokenIterator::new(line).peekable();
+    match next(&mut tokens) {
+        Token::Newline | Token::Eof | Token::Doc(_) => return Ok(false),
+        Token::RightBrace => return Ok(true),
+        Token::Ident(_) => {}
+        x => return Err(format!("Expected property, got {x:?}")),
+    }
+    match next(&mut tokens) {
+        Token::Ident(c) if c == "const" => {
+            let Token::Ident(_) = next(&mut tokens) else {
+                return Err("Expected property input name".to_string());
+            };
+            check_expr(&mut tokens)?;
+            end_of_line(&mut tokens)?;
+        }
+        // `name output_name output / input_name input`
+        Token::Ident(_) => {
+            let after_names = skip_word(skip_word(line));
+            let Some((output, input)) = after_names.split_once('/') else {
+                return Err("Expected /".to_string());
+            };
+            let mut tokens = TokenIterator::new(output).peekable();
+            check_expr(&mut tokens)?;
+            end_of_line(&mut tokens)?;
+            let mut tokens = TokenIterator::new(input).peekable();
+            let Token::Ident(_) = next(&mut tokens) else {
+                return Err("Expected property input name".to_string());
+            };
+            check_expr(&mut tokens)?;
+            end_of_line(&mut tokens)?;
+        }
+        x => return Err(format!("Expected property input name, got {x:?}")),
+    }
+    Ok(false)
+}
+
+fn check_expr(tokens: &mut Iter) -> Result<(), String> {
+    let expr = gnu_units::parse_expr(tokens).to_string();
+    // Rink keeps the errors of an expression inside it, as `<error: ...>`
+    match expr.split_once("<error: ") {
+        Some((_, error)) => Err(error.split_once('>').map_or(error, |(e, _)| e).to_string()),
+        None => Ok(()),
+    }
+}
+
+fn skip_word(s: &str) -> &str {
+    let s = s.trim_start();
+    s.find(char::is_whitespace).map_or("", |i| &s[i..])
+}
+
+fn end_of_line(tokens: &mut Iter) -> Result<(), String> {
+    match next(tokens) {
+        Token::Newline | Token::Eof => Ok(()),
+        x => Err(format!("Unexpected {x:?} after definition")),
+    }
+}
+
+fn next(tokens: &mut Iter) -> Token {
+    tokens.next().unwrap_or(Token::Eof)
+}
+
+fn peek(tokens: &mut Iter) -> Token {
+    tokens.peek().cloned().unwrap_or(Token::Eof)
+}
+
+/// Rink reports errors by definition name only, find the line defining the
+/// first name mentioned in the message.
+fn find_line(content: &str, message: &str) -> Option<usize> {
+    let defined: Vec<(usize, &str)> = content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, l)| {
+            l.split_whitespace()
+                .next()
+                .filter(|w| !w.starts_with('#'))
+                .map(|w| (i + 1, w.trim_end_matches('-')))
+        })
+        .collect();
+    message
+        .split(|c: char| c.is_whitespace() || matches!(c, ':' | ',' | '`'))
+        .find_map(|word| {
+            defined
+                .iter()
+                .find(|(_, name)| !word.is_empty() && *name == word)
+                .map(|(line, _)| *line)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::{check_defs, currency::Rates};
+
+    #[test]
+    fn line_of_definition() {
+        let content = "# lengths\nfoo 3 m\n\nbar- 1000\nbaz 2 foo\n";
+        assert_eq!(
+            find_line(content, "unit baz is malformed: No such unit qux"),
+            Some(5)
+        );
+        assert_eq!(find_line(content, "`foo` is defined twice"), Some(2));
+        // Prefixes are named without their dash, comments define nothing
+        assert_eq!(find_line(content, "bar: bad prefix"), Some(4));
+        assert_eq!(find_line(content, "lengths"), None);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let content = "foo 3 m\nbaz 1 +\nqux )(\nlong 1 + \\\n  2 )\nquux 4 foo\n";
+        let (checked, errors) = check_syntax(content);
+        assert_eq!(
+            errors,
+            [
+                (2, "Expected term, got Eof".to_string()),
+                (3, "Expected term, got RPar".to_string()),
+                (4, "Unexpected RPar after definition".to_string()),
+            ]
+        );
+        // The invalid lines are blanked, the others keep their line
+        assert_eq!(checked, "foo 3 m\n\n\n\n\nquux 4 foo");
+    }
+
+    #[test]
+    fn syntax_of_directives_and_substances() {
+        let content = "!category len Lengths\n!endcategory\n!endcategory\n!frobnicate\n\
+                       water {\n  density mass gram / volume cm^3\n\n  molar const molar_mass \\\n    18 g / mol\n}\n\
+                       salt {\n  density mass gram\n}\n";
+        let (_, errors) = check_syntax(content);
+        assert_eq!(
+            errors,
+            [
+                (3, "Stray endcategory directive".to_string()),
+                (4, "Unknown directive !frobnicate".to_string()),
+                (12, "Expected /".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn bundled_definitions_are_valid() {
+        for file in [rink_core::DEFAULT_FILE, rink_core::CURRENCY_FILE]
+            .into_iter()
+            .flatten()
+        {
+            assert_eq!(check_syntax(file).1, []);
+        }
+    }
+
+    #[test]
+    fn check_defs_reports_bad_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("a.units");
+        let bad = dir.path().join("b.units");
+        fs::write(&good, "foo 3 m\n").unwrap();
+        fs::write(&bad, "bar 2 foo\nbaz 1 +\nqux )(\nquux 2 nosuchunit\n").unwrap();
+        let rates = || Rates {
+            defs: rink_core::ast::Defs { defs: Vec::new() },
+            fetched: None,
+        };
+        assert!(check_defs(rates(), std::slice::from_ref(&good)));
+        assert!(!check_defs(rates(), &[good.clone(), bad.clone()]));
+
+        let mut ctx = rink_core::simple_context().unwrap();
+        let errors: Vec<String> = load_files(&mut ctx, &[good, bad.clone()])
+            .iter()
+            .map(|e| {
+                assert_eq!(e.path, bad);
+                format!("{:?}: {}", e.line, e.message)
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "Some(2): Expected term, got Eof",
+                "Some(3): Expected term, got RPar",
+                "Some(4): unit quux is malformed: No such unit nosuchunit, did you mean northerncubit?",
+            ]
+        );
+    }
+}
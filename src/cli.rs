@@ -4,9 +4,15 @@ use crate::apps;
 #[cfg(feature = "calc")]
 use crate::calc;
 #[cfg(feature = "hyprland")]
+use crate::hyprctl_binds;
+#[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
 use crate::hyprctl_clients;
-#[cfg(feature = "hyprland")]
+#[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
 use crate::hyprctl_hide;
+#[cfg(feature = "hyprland")]
+use crate::hyprctl_monitors;
+#[cfg(feature = "hyprland")]
+use crate::hyprctl_workspaces;
 #[cfg(feature = "paru")]
 use crate::paru;
 #[cfg(feature = "systemd")]
@@ -23,18 +29,55 @@ use crate::systemd_services;
 pub fn parse_mode(mode: &Mode) -> Box<dyn SkimRun> {
     match mode {
         #[cfg(feature = "apps")]
-        Mode::Apps {} => Box::new(apps::Apps),
+        Mode::Apps { .. } => Box::new(apps::Apps),
         #[cfg(feature = "calc")]
-        Mode::Calc { .. } => Box::new(calc::Calc),
-        #[cfg(feature = "hyprland")]
-        Mode::HyprctlClients {} => Box::new(hyprctl_clients::HyprctlClients),
+        Mode::Calc { calc, .. } => Box::new(calc.calc()),
+        #[cfg(feature = "calc")]
+        Mode::CalcHistory { calc, .. } => {
+            Box::new(calc::history::CalcHistory { calc: calc.calc() })
+        }
+        #[cfg(feature = "calc")]
+        Mode::CalcProg { int_type, .. } => Box::new(calc::prog::Prog { ty: *int_type }),
         #[cfg(feature = "hyprland")]
-        Mode::HyprctlHide { ignore_class, swap } => Box::new(hyprctl_hide::HyprctlHide {
+        Mode::HyprctlBinds { ignore_class } => Box::new(hyprctl_binds::HyprctlBinds {
             ignore_class: ignore_class.clone(),
-            swap: swap.clone(),
+        }),
+        #[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
+        Mode::HyprctlClients {
+            format,
+            include_current,
+            group_by,
+            ..
+        } => Box::new(hyprctl_clients::HyprctlClients {
+            format: format.clone(),
+            include_current: *include_current,
+            group_by: *group_by,
+        }),
+        #[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
+        Mode::HyprctlHide {
+            ignore_class,
+            stash,
+            all,
+            ..
+        } => Box::new(hyprctl_hide::HyprctlHide {
+            ignore_class: ignore_class.clone(),
+            stash: stash.clone(),
+            all: *all,
+        }),
+        #[cfg(feature = "hyprland")]
+        Mode::HyprctlMonitors { .. } => Box::new(hyprctl_monitors::HyprctlMonitors),
+        #[cfg(feature = "hyprland")]
+        Mode::HyprctlWorkspaces { window, .. } => Box::new(hyprctl_workspaces::HyprctlWorkspaces {
+            window: window.clone(),
         }),
         #[cfg(feature = "systemd")]
-        Mode::SystemdServices {} => Box::new(systemd_services::SystemdServices),
+        Mode::SystemdServices { .. } => Box::new(systemd_services::SystemdUnits {
+            types: vec![systemd_services::UnitType::Service],
+        }),
+        #[cfg(feature = "systemd")]
+        Mode::SystemdUnits { types } => Box::new(systemd_services::SystemdUnits {
+            types: types.clone(),
+        }),
         #[cfg(feature = "paru")]
         Mode::Paru {} => Box::new(paru::Paru),
         #[allow(unreachable_patterns)]
@@ -51,27 +94,190 @@ pub struct Cli {
     #[arg(short, long, global = true, value_delimiter = ',')]
     pub modes: Vec<String>,
 }
+/// Flags of the calculator modes evaluating expressions
+#[cfg(feature = "calc")]
+#[derive(Debug, Clone, clap::Args)]
+pub struct CalcArgs {
+    /// Where to fetch currency rates from, an http(s) URL or a local JSON file
+    #[arg(long, default_value = calc::currency::DEFAULT_RATES_URL)]
+    rates_url: String,
+    /// How long cached currency rates stay valid, in seconds
+    #[arg(long, default_value_t = calc::currency::DEFAULT_RATES_TTL)]
+    rates_ttl: u64,
+    /// Only use cached or bundled currency rates
+    #[arg(long, default_value = "false")]
+    offline: bool,
+    #[command(flatten)]
+    format: calc::format::OutputFormat,
+    /// Evaluate at this time instead of the current one, as RFC 3339
+    #[arg(long, value_parser = calc::time::parse_clock)]
+    now: Option<calc::time::Clock>,
+}
+
+#[cfg(feature = "calc")]
+impl CalcArgs {
+    fn calc(&self) -> calc::Calc {
+        calc::Calc {
+            rates: calc::currency::RatesSource {
+                url: self.rates_url.clone(),
+                ttl: std::time::Duration::from_secs(self.rates_ttl),
+            },
+            offline: self.offline,
+            format: self.format.clone(),
+            clock: self.now.unwrap_or_default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, clap::Subcommand)]
 pub enum Mode {
     #[cfg(feature = "apps")]
-    Apps {},
+    Apps {
+        /// Update the flatpak or snap app exported by this desktop file
+        #[arg(long)]
+        update: Option<std::path::PathBuf>,
+        /// Uninstall the flatpak or snap app exported by this desktop file
+        #[arg(long, conflicts_with = "update")]
+        uninstall: Option<std::path::PathBuf>,
+    },
     #[cfg(feature = "calc")]
     Calc {
         #[arg(long, default_value = "false")]
         eval: bool,
+        #[command(flatten)]
+        calc: CalcArgs,
+        /// With --eval, save the result to the history and copy it to the clipboard
+        #[arg(long, default_value = "false", requires = "eval")]
+        save: bool,
+        /// With --save, the line of the representations list to copy
+        #[arg(long, requires = "save")]
+        pick: Option<String>,
+        /// Validate the user definition files in the config dir and exit
+        #[arg(long, default_value = "false")]
+        check_defs: bool,
+        #[arg(allow_hyphen_values = true)]
+        expr: Vec<String>,
+    },
+    #[cfg(feature = "calc")]
+    CalcHistory {
+        /// Evaluate the history entry at this index again
+        #[arg(long)]
+        rerun: Option<usize>,
+        #[command(flatten)]
+        calc: CalcArgs,
+    },
+    #[cfg(feature = "calc")]
+    CalcProg {
+        #[arg(long, default_value = "false")]
+        eval: bool,
+        /// With --eval, save the result to the history and copy it to the clipboard
+        #[arg(long, default_value = "false", requires = "eval")]
+        save: bool,
+        /// With --save, the representation to copy instead of the decimal value
+        #[arg(long, requires = "save")]
+        pick: Option<String>,
+        /// Integer type of the literals, results wrap at its width
+        #[arg(long = "type", value_enum, default_value_t)]
+        int_type: calc::prog::IntType,
+        #[arg(allow_hyphen_values = true)]
         expr: Vec<String>,
     },
     #[cfg(feature = "hyprland")]
-    HyprctlClients {},
-    #[cfg(feature = "hyprland")]
+    HyprctlBinds {
+        /// Class of the terminal running the picker, binds then act on the window focused before it
+        #[arg(long)]
+        ignore_class: Option<String>,
+    },
+    #[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
+    HyprctlClients {
+        /// Line of each window, with the fields `{title}` `{class}` `{workspace}` `{monitor}`
+        /// `{address}` `{pid}` `{initial_title}` `{initial_class}` and `{state}`
+        #[arg(long, default_value = hyprctl_clients::DEFAULT_FORMAT)]
+        format: String,
+        /// List the focused window too
+        #[arg(long, default_value = "false")]
+        include_current: bool,
+        /// Sort the windows by workspace or monitor, and prefix them with it
+        #[arg(long, value_enum)]
+        group_by: Option<hyprctl_clients::GroupBy>,
+        /// Run this action on the window at --address and exit
+        #[arg(long, value_enum, requires = "address")]
+        action: Option<hyprctl_clients::WindowAction>,
+        #[arg(long)]
+        address: Option<String>,
+    },
+    #[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
     HyprctlHide {
         #[arg(long)]
         ignore_class: Option<String>,
+        /// Swap the focused window with the hidden window at this address and exit
         #[arg(long)]
         swap: Option<String>,
+        /// Bring the hidden window at this address to the current workspace and exit
+        #[arg(long, conflicts_with = "swap")]
+        unhide: Option<String>,
+        /// The stash to list and hide windows into, the special workspace `special:<STASH>`
+        #[arg(long, default_value = hyprctl_hide::DEFAULT_STASH)]
+        stash: String,
+        /// List the windows of all stashes (every special workspace)
+        #[arg(long, default_value = "false")]
+        all: bool,
+        /// Hide the focused window into the stash and exit
+        #[arg(long, default_value = "false")]
+        hide_current: bool,
+        /// Bring all windows of the stash to the current workspace and exit
+        #[arg(long, default_value = "false")]
+        restore: bool,
+        /// Bring all windows of the stash holding the window at this address and exit
+        #[arg(long)]
+        restore_with: Option<String>,
+    },
+    #[cfg(feature = "hyprland")]
+    HyprctlMonitors {
+        /// Move the current workspace to the monitor with this name and exit
+        #[arg(long)]
+        move_workspace: Option<String>,
+        /// Turn the monitor with this name off or back on and exit
+        #[arg(long, conflicts_with = "move_workspace")]
+        toggle_dpms: Option<String>,
+        /// Apply the saved layout profile with this name and exit
+        #[arg(long, conflicts_with_all = ["move_workspace", "toggle_dpms"])]
+        profile: Option<String>,
+    },
+    #[cfg(feature = "hyprland")]
+    HyprctlWorkspaces {
+        /// Move the window focused before the picker to the workspace with this id and exit
+        #[arg(long, allow_hyphen_values = true)]
+        move_window: Option<i64>,
+        /// Rename the workspace with this id to NAME and exit
+        #[arg(long, allow_hyphen_values = true, conflicts_with = "move_window")]
+        rename: Option<i64>,
+        /// Create and switch to a workspace named NAME and exit
+        #[arg(long, default_value = "false", conflicts_with_all = ["move_window", "rename"])]
+        create: bool,
+        /// Move the window at this address to the selected or created workspace, instead of the focused one
+        #[arg(long, conflicts_with = "rename")]
+        window: Option<String>,
+        #[arg(allow_hyphen_values = true)]
+        name: Vec<String>,
+    },
+    #[cfg(feature = "systemd")]
+    SystemdServices {
+        /// Run this action on --unit and exit, once its job finished
+        #[arg(long, value_enum, requires = "unit")]
+        action: Option<systemd_services::UnitAction>,
+        #[arg(long)]
+        unit: Option<String>,
+        /// The systemd instance of --unit
+        #[arg(long, value_enum, default_value = "system")]
+        bus: systemd_services::Bus,
     },
     #[cfg(feature = "systemd")]
-    SystemdServices {},
+    SystemdUnits {
+        /// Only list units of these types, all of them by default
+        #[arg(long = "type", value_enum, value_delimiter = ',')]
+        types: Vec<systemd_services::UnitType>,
+    },
     #[cfg(feature = "paru")]
     Paru {},
 }
@@ -82,12 +288,24 @@ impl std::fmt::Display for Mode {
             Mode::Apps { .. } => "apps",
             #[cfg(feature = "calc")]
             Mode::Calc { .. } => "calc",
+            #[cfg(feature = "calc")]
+            Mode::CalcHistory { .. } => "calc-history",
+            #[cfg(feature = "calc")]
+            Mode::CalcProg { .. } => "calc-prog",
             #[cfg(feature = "hyprland")]
+            Mode::HyprctlBinds { .. } => "hyprctl-binds",
+            #[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
             Mode::HyprctlClients { .. } => "hyprctl-clients",
-            #[cfg(feature = "hyprland")]
+            #[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
             Mode::HyprctlHide { .. } => "hyprctl-hide",
+            #[cfg(feature = "hyprland")]
+            Mode::HyprctlMonitors { .. } => "hyprctl-monitors",
+            #[cfg(feature = "hyprland")]
+            Mode::HyprctlWorkspaces { .. } => "hyprctl-workspaces",
             #[cfg(feature = "systemd")]
             Mode::SystemdServices { .. } => "systemd-services",
+            #[cfg(feature = "systemd")]
+            Mode::SystemdUnits { .. } => "systemd-units",
             #[cfg(feature = "paru")]
             Mode::Paru { .. } => "paru",
         };
@@ -0,0 +1,148 @@
+//! The compositors the window modes run on.
+//! Every backend speaks in the Hyprland model, the richest of them:
+//! windows are [`Client`]s, commands are [`Dispatch`]es and live updates are [`Event`]s.
+
+#[cfg(feature = "niri")]
+pub mod niri;
+#[cfg(feature = "sway")]
+pub mod sway;
+
+use std::{slice, sync::Arc};
+
+use anyhow::{Result, bail};
+
+#[cfg(feature = "hyprland")]
+use crate::hyprland::Hyprland;
+use crate::{
+    hyprctl_hide::stash_workspace,
+    hyprland::{Client, Dispatch, Monitor, Workspace, WorkspaceRef, events::Event},
+};
+
+/// Whether the windows of a workspace are stashed, shared with the skim items
+pub type StashPredicate = Arc<dyn Fn(&WorkspaceRef) -> bool + Send + Sync>;
+
+/// A window manager the window modes can list and move windows on
+pub trait Compositor {
+    /// Name shown in errors
+    fn name(&self) -> &'static str;
+
+    /// All windows, `focus_history_id` 0 being the focused one
+    ///
+    /// # Errors
+    /// Returns an error if the compositor can not be reached.
+    fn clients(&self) -> Result<Vec<Client>>;
+
+    /// # Errors
+    /// Returns an error if the compositor can not be reached.
+    fn active_window(&self) -> Result<Option<Client>> {
+        Ok(self
+            .clients()?
+            .into_iter()
+            .find(|c| c.focus_history_id == 0))
+    }
+
+    /// The window focused before the focused one, which is the picker's terminal while it runs
+    ///
+    /// # Errors
+    /// Returns an error if the compositor can not be reached.
+    fn previous_window(&self) -> Result<Option<Client>> {
+        Ok(self
+            .clients()?
+            .into_iter()
+            .filter(|c| c.focus_history_id > 0)
+            .min_by_key(|c| c.focus_history_id))
+    }
+
+    /// # Errors
+    /// Returns an error if the compositor can not be reached.
+    fn active_workspace(&self) -> Result<Workspace>;
+
+    /// The outputs, with the ids `Client::monitor` refers to
+    ///
+    /// # Errors
+    /// Returns an error if the compositor can not be reached.
+    fn monitors(&self) -> Result<Vec<Monitor>>;
+
+    /// The workspace holding the windows of `stash`
+    fn stash_workspace(&self, stash: &str) -> String {
+        stash_workspace(stash)
+    }
+
+    /// Whether the windows of a workspace are stashed, `hyprctl-hide --all` lists them.
+    /// `stash` is the one `hyprctl-hide` was given, for compositors telling stashes by name.
+    fn stash_predicate(&self, _stash: &str) -> StashPredicate {
+        Arc::new(WorkspaceRef::is_special)
+    }
+
+    /// Runs the dispatchers in a single request
+    ///
+    /// # Errors
+    /// Returns an error if the request fails, or if the compositor rejects
+    /// or does not support a dispatcher.
+    fn batch(&self, dispatches: &[Dispatch]) -> Result<()>;
+
+    /// # Errors
+    /// Returns an error if the request fails, or if the compositor rejects
+    /// or does not support the dispatcher.
+    fn dispatch(&self, dispatch: &Dispatch) -> Result<()> {
+        self.batch(slice::from_ref(dispatch))
+    }
+
+    /// Window events, for the live lists
+    ///
+    /// # Errors
+    /// Returns an error if the compositor has no event stream or it can not be reached.
+    fn events(&self) -> Result<Box<dyn Iterator<Item = Event> + Send>> {
+        bail!("{} does not report window events", self.name())
+    }
+}
+
+#[cfg(feature = "hyprland")]
+impl Compositor for Hyprland {
+    fn name(&self) -> &'static str {
+        "Hyprland"
+    }
+    fn clients(&self) -> Result<Vec<Client>> {
+        Hyprland::clients(self)
+    }
+    fn active_window(&self) -> Result<Option<Client>> {
+        Hyprland::active_window(self)
+    }
+    fn active_workspace(&self) -> Result<Workspace> {
+        Hyprland::active_workspace(self)
+    }
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        Hyprland::monitors(self)
+    }
+    fn batch(&self, dispatches: &[Dispatch]) -> Result<()> {
+        Hyprland::batch(self, dispatches)
+    }
+    fn dispatch(&self, dispatch: &Dispatch) -> Result<()> {
+        Hyprland::dispatch(self, dispatch)
+    }
+    fn events(&self) -> Result<Box<dyn Iterator<Item = Event> + Send>> {
+        Ok(Box::new(Hyprland::events(self)?))
+    }
+}
+
+/// The compositor of the running session, from the sockets it exports
+///
+/// # Errors
+/// Returns an error if no supported compositor is running.
+pub fn from_env() -> Result<Box<dyn Compositor>> {
+    #[cfg(feature = "hyprland")]
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        return Ok(Box::new(Hyprland::from_env()?));
+    }
+    #[cfg(feature = "sway")]
+    if let Some(socket) = std::env::var_os("SWAYSOCK").or_else(|| std::env::var_os("I3SOCK")) {
+        return Ok(Box::new(sway::Sway::new(socket)));
+    }
+    #[cfg(feature = "niri")]
+    if let Some(socket) = std::env::var_os("NIRI_SOCKET") {
+        return Ok(Box::new(niri::Niri::new(socket)));
+    }
+    bail!(
+        "No supported compositor found, none of HYPRLAND_INSTANCE_SIGNATURE, SWAYSOCK or NIRI_SOCKET is set, or its feature is not enabled in this build"
+    )
+}
@@ -0,0 +1,661 @@
+//! niri, through its JSON IPC on `$NIRI_SOCKET`.
+//! niri has no hidden workspaces, the stash `<name>` is the named workspace `<name>`
+//! (declared with `workspace "<name>"` in niri's config).
+
+use std::{
+    collections::HashMap,
+    io::{BufRead as _, BufReader, Write as _},
+    net::Shutdown,
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context as _, Result, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use super::{Compositor, StashPredicate};
+use crate::{
+    hyprctl_hide::DEFAULT_STASH,
+    hyprland::{Client, Dispatch, Monitor, Workspace, WorkspaceRef, events::Event},
+};
+
+static TIMEOUT: Duration = Duration::from_secs(5);
+
+/// From `Windows`, `FocusedWindow` and the window events
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct NiriWindow {
+    pub id: u64,
+    pub title: Option<String>,
+    pub app_id: Option<String>,
+    pub pid: Option<i32>,
+    pub workspace_id: Option<u64>,
+    pub is_focused: bool,
+    pub is_floating: bool,
+    /// Newer niri versions only
+    pub focus_timestamp: Option<Timestamp>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(default)]
+pub struct Timestamp {
+    pub secs: u64,
+    pub nanos: u32,
+}
+
+/// From `Workspaces` and the workspace events
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct NiriWorkspace {
+    pub id: u64,
+    /// Position on its output, from 1
+    pub idx: u8,
+    pub name: Option<String>,
+    pub output: Option<String>,
+    pub is_active: bool,
+    pub is_focused: bool,
+}
+
+impl NiriWorkspace {
+    fn workspace_ref(&self) -> WorkspaceRef {
+        WorkspaceRef {
+            id: i64::try_from(self.id).unwrap_or_default(),
+            // Unnamed workspaces go by their position, like Hyprland's numbered ones
+            name: self.name.clone().unwrap_or_else(|| self.idx.to_string()),
+        }
+    }
+}
+
+/// From `Outputs`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct NiriOutput {
+    name: String,
+    make: String,
+    model: String,
+    logical: Option<Logical>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+struct Logical {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    scale: f64,
+}
+
+/// Turns niri's windows into clients, `outputs` gives the monitor ids
+fn niri_clients(
+    windows: Vec<NiriWindow>,
+    workspaces: &[NiriWorkspace],
+    outputs: &[String],
+) -> Vec<Client> {
+    let workspace = |id: Option<u64>| workspaces.iter().find(|w| Some(w.id) == id);
+    let mut clients: Vec<(Option<Timestamp>, Client)> = windows
+        .into_iter()
+        .map(|w| {
+            let ws = workspace(w.workspace_id);
+            let monitor = ws
+                .and_then(|ws| outputs.iter().position(|o| Some(o) == ws.output.as_ref()))
+                .map_or(-1, |i| i64::try_from(i).unwrap_or_default());
+            let client = Client {
+                address: w.id.to_string(),
+                mapped: true,
+                workspace: ws.map(NiriWorkspace::workspace_ref).unwrap_or_default(),
+                floating: w.is_floating,
+                monitor,
+                class: w.app_id.unwrap_or_default(),
+                title: w.title.unwrap_or_default(),
+                pid: w.pid.unwrap_or_default(),
+                focus_history_id: if w.is_focused { 0 } else { -1 },
+                ..Client::default()
+            };
+            (w.focus_timestamp, client)
+        })
+        .collect();
+    // Focused first, then by last focus when niri reports it
+    clients.sort_by(|(ta, a), (tb, b)| {
+        (a.focus_history_id != 0)
+            .cmp(&(b.focus_history_id != 0))
+            .then(tb.cmp(ta))
+    });
+    let offset = i32::from(clients.first().is_none_or(|(_, c)| c.focus_history_id != 0));
+    (offset..)
+        .zip(clients)
+        .map(|(history, (_, mut client))| {
+            client.focus_history_id = history;
+            client
+        })
+        .collect()
+}
+
+/// A workspace reference of niri's actions, from a dispatcher argument
+/// (id, `name:<name>` or `special:<stash>`)
+fn reference(workspace: &str) -> Value {
+    if let Some(name) = workspace
+        .strip_prefix("name:")
+        .or_else(|| workspace.strip_prefix("special:"))
+    {
+        json!({ "Name": name })
+    } else if let Ok(id) = workspace.parse::<u64>() {
+        json!({ "Id": id })
+    } else {
+        json!({ "Name": workspace })
+    }
+}
+
+fn window_id(address: &str) -> Result<u64> {
+    address
+        .parse()
+        .with_context(|| format!("Invalid niri window id {address}"))
+}
+
+/// Client for niri's IPC socket, one connection per request
+#[derive(Clone, Debug)]
+pub struct Niri {
+    socket: PathBuf,
+}
+
+impl Niri {
+    /// The IPC socket at `socket`, a fake server can listen there
+    #[must_use]
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+        }
+    }
+
+    /// Sends a request and returns its reply, leaving the connection open for the event stream
+    fn send(&self, request: &Value) -> Result<(Value, BufReader<UnixStream>)> {
+        let mut stream = UnixStream::connect(&self.socket)
+            .with_context(|| format!("Failed to connect to {}", self.socket.display()))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        writeln!(stream, "{request}").context("Failed to send niri request")?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader
+            .read_line(&mut line)
+            .context("Failed to read niri reply")?;
+        let reply: Value = serde_json::from_str(&line).context("Failed to parse niri reply")?;
+        if let Some(err) = reply.get("Err") {
+            bail!(
+                "niri rejected `{request}`: {}",
+                err.as_str().unwrap_or_default()
+            );
+        }
+        let ok = reply
+            .get("Ok")
+            .cloned()
+            .with_context(|| format!("Unexpected niri reply: {}", line.trim()))?;
+        Ok((ok, reader))
+    }
+
+    /// Sends a request and returns the `Ok` payload of its reply
+    ///
+    /// # Errors
+    /// Returns an error if the socket can not be reached or niri rejects the request.
+    pub fn request(&self, request: &Value) -> Result<Value> {
+        let (reply, reader) = self.send(request)?;
+        let _ = reader.into_inner().shutdown(Shutdown::Both);
+        Ok(reply)
+    }
+
+    /// Runs a query like `Windows` and parses the payload of the same name
+    fn get<T: serde::de::DeserializeOwned>(&self, query: &str) -> Result<T> {
+        let mut reply = self.request(&json!(query))?;
+        serde_json::from_value(reply[query].take())
+            .with_context(|| format!("Failed to parse niri {query} reply"))
+    }
+
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn workspaces(&self) -> Result<Vec<NiriWorkspace>> {
+        self.get("Workspaces")
+    }
+
+    fn output_names(&self) -> Result<Vec<String>> {
+        let outputs: HashMap<String, NiriOutput> = self.get("Outputs")?;
+        let mut names: Vec<String> = outputs.into_keys().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// The action doing `dispatch`
+    fn action(dispatch: &Dispatch) -> Result<Value> {
+        Ok(match dispatch {
+            Dispatch::FocusWindow { address } => {
+                json!({ "FocusWindow": { "id": window_id(address)? } })
+            }
+            Dispatch::MoveToWorkspaceSilent { workspace, address } => json!({
+                "MoveWindowToWorkspace": {
+                    "window_id": window_id(address)?,
+                    "reference": reference(workspace),
+                    "focus": false,
+                }
+            }),
+            Dispatch::MoveToWorkspace { workspace } => json!({
+                "MoveWindowToWorkspace": {
+                    "window_id": null,
+                    "reference": reference(workspace),
+                    "focus": true,
+                }
+            }),
+            Dispatch::Workspace { workspace } => {
+                json!({ "FocusWorkspace": { "reference": reference(workspace) } })
+            }
+            Dispatch::RenameWorkspace { id, name } => json!({
+                "SetWorkspaceName": { "name": name, "workspace": { "Id": id } }
+            }),
+            Dispatch::CloseWindow { address } => {
+                json!({ "CloseWindow": { "id": window_id(address)? } })
+            }
+            Dispatch::ToggleFloating { address } => {
+                json!({ "ToggleWindowFloating": { "id": window_id(address)? } })
+            }
+            Dispatch::Fullscreen => json!({ "FullscreenWindow": { "id": null } }),
+            Dispatch::CenterWindow => json!({ "CenterWindow": { "id": null } }),
+            Dispatch::KillWindow { .. }
+            | Dispatch::Pin { .. }
+            | Dispatch::ToggleSpecialWorkspace { .. }
+            | Dispatch::ToggleGroup
+            | Dispatch::MoveIntoGroup { .. }
+            | Dispatch::FocusMonitor { .. }
+            | Dispatch::MoveCurrentWorkspaceToMonitor { .. }
+            | Dispatch::ToggleDpms { .. }
+            | Dispatch::Raw { .. } => bail!("`{dispatch}` is not supported on niri"),
+        })
+    }
+}
+
+impl Compositor for Niri {
+    fn name(&self) -> &'static str {
+        "niri"
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        Ok(niri_clients(
+            self.get("Windows")?,
+            &self.workspaces()?,
+            &self.output_names()?,
+        ))
+    }
+
+    fn active_workspace(&self) -> Result<Workspace> {
+        let active = self
+            .workspaces()?
+            .into_iter()
+            .find(|w| w.is_focused)
+            .context("niri reports no focused workspace")?;
+        let ws = active.workspace_ref();
+        Ok(Workspace {
+            id: ws.id,
+            name: ws.name,
+            monitor: active.output.unwrap_or_default(),
+            ..Workspace::default()
+        })
+    }
+
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        let mut outputs: Vec<NiriOutput> = self
+            .get::<HashMap<String, NiriOutput>>("Outputs")?
+            .into_values()
+            .collect();
+        outputs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok((0..)
+            .zip(outputs)
+            .map(|(id, o)| {
+                let logical = o.logical.unwrap_or_default();
+                Monitor {
+                    id,
+                    name: o.name,
+                    make: o.make,
+                    model: o.model,
+                    width: logical.width,
+                    height: logical.height,
+                    x: logical.x,
+                    y: logical.y,
+                    scale: logical.scale,
+                    ..Monitor::default()
+                }
+            })
+            .collect())
+    }
+
+    fn stash_workspace(&self, stash: &str) -> String {
+        stash.to_string()
+    }
+
+    fn stash_predicate(&self, stash: &str) -> StashPredicate {
+        // Other named workspaces are regular ones, only the stashes in use count
+        let stashes = [stash.to_string(), DEFAULT_STASH.to_string()];
+        Arc::new(move |ws| stashes.contains(&ws.name))
+    }
+
+    /// niri has no batches, the actions are sent one after the other
+    fn batch(&self, dispatches: &[Dispatch]) -> Result<()> {
+        let actions = dispatches
+            .iter()
+            .map(Self::action)
+            .collect::<Result<Vec<_>>>()?;
+        for action in actions {
+            self.request(&json!({ "Action": action }))?;
+        }
+        Ok(())
+    }
+
+    fn events(&self) -> Result<Box<dyn Iterator<Item = Event> + Send>> {
+        let (_, mut reader) = self.send(&json!("EventStream"))?;
+        // Events come whenever windows change
+        reader.get_ref().set_read_timeout(None)?;
+        let mut workspaces: HashMap<u64, WorkspaceRef> = self
+            .workspaces()?
+            .iter()
+            .map(|w| (w.id, w.workspace_ref()))
+            .collect();
+        let mut line = String::new();
+        Ok(Box::new(
+            std::iter::from_fn(move || {
+                line.clear();
+                reader.read_line(&mut line).ok().filter(|n| *n > 0)?;
+                let event: Value = serde_json::from_str(&line).unwrap_or_default();
+                Some(stream_event(&event, &mut workspaces))
+            })
+            .flatten(),
+        ))
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: &Value) -> Option<T> {
+    serde_json::from_value(value.clone()).ok()
+}
+
+/// Translates an event of the event stream, tracking the workspace names
+fn stream_event(event: &Value, workspaces: &mut HashMap<u64, WorkspaceRef>) -> Vec<Event> {
+    let window_events = |w: NiriWindow, workspaces: &HashMap<u64, WorkspaceRef>| {
+        let address = w.id.to_string();
+        vec![
+            // Also updates known windows
+            Event::OpenWindow {
+                address: address.clone(),
+                workspace: w
+                    .workspace_id
+                    .and_then(|id| workspaces.get(&id))
+                    .cloned()
+                    .unwrap_or_default(),
+                class: w.app_id.unwrap_or_default(),
+                title: w.title.unwrap_or_default(),
+            },
+            Event::ChangeFloatingMode {
+                address,
+                floating: w.is_floating,
+            },
+        ]
+    };
+    if let Some(list) = event["WorkspacesChanged"]["workspaces"].as_array() {
+        *workspaces = list
+            .iter()
+            .filter_map(parse::<NiriWorkspace>)
+            .map(|w| (w.id, w.workspace_ref()))
+            .collect();
+        Vec::new()
+    } else if let Some(list) = event["WindowsChanged"]["windows"].as_array() {
+        list.iter()
+            .filter_map(parse::<NiriWindow>)
+            .flat_map(|w| window_events(w, workspaces))
+            .collect()
+    } else if let Some(window) = parse::<NiriWindow>(&event["WindowOpenedOrChanged"]["window"]) {
+        window_events(window, workspaces)
+    } else if let Some(id) = event["WindowClosed"]["id"].as_u64() {
+        vec![Event::CloseWindow {
+            address: id.to_string(),
+        }]
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::net::UnixListener, thread::JoinHandle};
+
+    use super::*;
+
+    fn fixture(name: &str) -> Value {
+        let path = format!(
+            "{}/tests/fixtures/niri/{name}.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let json = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+        serde_json::from_str(&json).unwrap()
+    }
+
+    /// The reply to the query `query`, with the recorded payload of the same name
+    fn recorded(query: &str, name: &str) -> Vec<String> {
+        vec![json!({ "Ok": { query: fixture(name) } }).to_string()]
+    }
+
+    /// A fake niri answering each connection with the next lines of `replies`.
+    /// Joining the handle returns the requests it got.
+    fn serve(replies: Vec<Vec<String>>) -> (tempfile::TempDir, Niri, JoinHandle<Vec<Value>>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            replies
+                .into_iter()
+                .map(|lines| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut request = String::new();
+                    reader.read_line(&mut request).unwrap();
+                    let mut stream = reader.into_inner();
+                    for line in lines {
+                        writeln!(stream, "{line}").unwrap();
+                    }
+                    serde_json::from_str(&request).unwrap()
+                })
+                .collect()
+        });
+        (dir, Niri::new(path), server)
+    }
+
+    fn workspace(id: i64, name: &str) -> WorkspaceRef {
+        WorkspaceRef {
+            id,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn clients() {
+        let (_dir, niri, server) = serve(vec![
+            recorded("Windows", "windows"),
+            recorded("Workspaces", "workspaces"),
+            recorded("Outputs", "outputs"),
+        ]);
+        let clients = niri.clients().unwrap();
+        let summary: Vec<_> = clients
+            .iter()
+            .map(|c| {
+                (
+                    c.address.as_str(),
+                    c.focus_history_id,
+                    c.workspace.clone(),
+                    c.monitor,
+                )
+            })
+            .collect();
+        // Focused first, then by last focus
+        assert_eq!(
+            summary,
+            [
+                ("12", 0, workspace(1, "1"), 0),
+                ("15", 1, workspace(1, "1"), 0),
+                ("22", 2, workspace(4, "1"), 1),
+                ("20", 3, workspace(3, "chat"), 0),
+                ("17", 4, workspace(2, "hidden"), 0),
+            ]
+        );
+        let mpv = &clients[2];
+        assert_eq!(
+            (mpv.class.as_str(), mpv.title.as_str()),
+            ("mpv", "talk.mkv - mpv")
+        );
+        assert!(mpv.floating);
+        assert_eq!(mpv.pid, 3420);
+        assert_eq!(
+            server.join().unwrap(),
+            [json!("Windows"), json!("Workspaces"), json!("Outputs")]
+        );
+    }
+
+    #[test]
+    fn workspaces_and_monitors() {
+        let (_dir, niri, _server) = serve(vec![
+            recorded("Workspaces", "workspaces"),
+            recorded("Outputs", "outputs"),
+        ]);
+        let active = niri.active_workspace().unwrap();
+        assert_eq!((active.id, active.name.as_str()), (1, "1"));
+        assert_eq!(active.monitor, "DP-1");
+        let monitors: Vec<_> = niri
+            .monitors()
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.id, m.name, m.width, m.x, m.scale))
+            .collect();
+        assert_eq!(
+            monitors,
+            [
+                (0, "DP-1".to_string(), 1706, 0, 1.5),
+                (1, "HDMI-A-1".to_string(), 1920, 1706, 2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn stash_predicate() {
+        let niri = Niri::new("/nonexistent");
+        let is_stash = niri.stash_predicate("chat");
+        // The stash in use and the default one, not every named workspace
+        assert!(is_stash(&workspace(3, "chat")));
+        assert!(is_stash(&workspace(2, DEFAULT_STASH)));
+        assert!(!is_stash(&workspace(5, "music")));
+        assert!(!is_stash(&workspace(1, "1")));
+        let is_stash = niri.stash_predicate(DEFAULT_STASH);
+        assert!(!is_stash(&workspace(3, "chat")));
+        assert_eq!(niri.stash_workspace("chat"), "chat");
+    }
+
+    #[test]
+    fn actions() {
+        let handled = || vec![json!({ "Ok": "Handled" }).to_string()];
+        let (_dir, niri, server) = serve(vec![
+            handled(),
+            handled(),
+            vec![json!({ "Err": "window not found" }).to_string()],
+        ]);
+        niri.batch(&[
+            Dispatch::MoveToWorkspaceSilent {
+                workspace: String::from("special:hidden"),
+                address: String::from("15"),
+            },
+            Dispatch::FocusWindow {
+                address: String::from("15"),
+            },
+        ])
+        .unwrap();
+        let err = niri
+            .dispatch(&Dispatch::CloseWindow {
+                address: String::from("99"),
+            })
+            .unwrap_err();
+        assert!(err.to_string().ends_with("window not found"), "{err}");
+        // Unsupported dispatchers fail before anything is sent
+        assert!(niri.dispatch(&Dispatch::ToggleGroup).is_err());
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                json!({ "Action": { "MoveWindowToWorkspace": {
+                    "window_id": 15,
+                    "reference": { "Name": "hidden" },
+                    "focus": false,
+                } } }),
+                json!({ "Action": { "FocusWindow": { "id": 15 } } }),
+                json!({ "Action": { "CloseWindow": { "id": 99 } } }),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_events() {
+        let window = |workspace_id: u64| {
+            json!({ "WindowOpenedOrChanged": { "window": {
+                "id": 30,
+                "title": "Inbox",
+                "app_id": "thunderbird",
+                "pid": 3500,
+                "workspace_id": workspace_id,
+                "is_focused": true,
+                "is_floating": false,
+            } } })
+            .to_string()
+        };
+        let mut renamed = fixture("workspaces");
+        renamed[2]["name"] = json!("mail");
+        let (_dir, niri, server) = serve(vec![
+            vec![
+                json!({ "Ok": "Handled" }).to_string(),
+                json!({ "WorkspaceActivated": { "id": 3, "focused": true } }).to_string(),
+                window(3),
+                json!({ "WorkspacesChanged": { "workspaces": renamed } }).to_string(),
+                window(3),
+                json!({ "WindowClosed": { "id": 15 } }).to_string(),
+            ],
+            // The workspaces known when the stream starts
+            recorded("Workspaces", "workspaces"),
+        ]);
+        let events: Vec<Event> = niri.events().unwrap().collect();
+        let opened = |name: &str| Event::OpenWindow {
+            address: String::from("30"),
+            workspace: workspace(3, name),
+            class: String::from("thunderbird"),
+            title: String::from("Inbox"),
+        };
+        let floating = Event::ChangeFloatingMode {
+            address: String::from("30"),
+            floating: false,
+        };
+        assert_eq!(
+            events,
+            [
+                opened("chat"),
+                floating.clone(),
+                opened("mail"),
+                floating,
+                Event::CloseWindow {
+                    address: String::from("15"),
+                },
+            ]
+        );
+        assert_eq!(
+            server.join().unwrap(),
+            [json!("EventStream"), json!("Workspaces")]
+        );
+    }
+
+    #[test]
+    fn unreachable_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = Niri::new(dir.path().join("none.sock"))
+            .clients()
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Failed to connect to"));
+    }
+}
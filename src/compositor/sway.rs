@@ -0,0 +1,741 @@
+//! Sway and i3, through the i3 IPC protocol on `$SWAYSOCK` (or `$I3SOCK`).
+//! The scratchpad is the only stash, it shows up as the `special:scratchpad` workspace.
+
+use std::{
+    io::{Read as _, Write as _},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::{Context as _, Result, bail};
+use serde::{Deserialize, de::DeserializeOwned};
+
+use super::Compositor;
+use crate::hyprland::{
+    Client, Dispatch, Monitor, Workspace, WorkspaceRef, events::Event, model::Fullscreen,
+};
+
+static MAGIC: &[u8] = b"i3-ipc";
+static TIMEOUT: Duration = Duration::from_secs(5);
+/// How the scratchpad appears in the window list
+pub static SCRATCHPAD: &str = "special:scratchpad";
+/// The workspace holding the scratchpad in the tree
+static SCRATCH_WORKSPACE: &str = "__i3_scratch";
+
+const RUN_COMMAND: u32 = 0;
+const GET_WORKSPACES: u32 = 1;
+const SUBSCRIBE: u32 = 2;
+const GET_TREE: u32 = 4;
+/// Events have the high bit of their type set
+const WINDOW_EVENT: u32 = 0x8000_0003;
+
+/// A container of the layout tree, from `GET_TREE`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Node {
+    pub id: i64,
+    pub name: Option<String>,
+    /// `root`, `output`, `workspace`, `con` or `floating_con`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Workspace number, -1 for named workspaces
+    pub num: Option<i64>,
+    pub focused: bool,
+    /// Ids of the children, most recently focused first
+    pub focus: Vec<i64>,
+    pub nodes: Vec<Node>,
+    pub floating_nodes: Vec<Node>,
+    pub rect: Rect,
+    /// Wayland windows
+    pub app_id: Option<String>,
+    /// X11 windows
+    pub window: Option<i64>,
+    pub window_properties: Option<WindowProperties>,
+    pub pid: i32,
+    pub sticky: bool,
+    /// 0 none, 1 workspace, 2 global
+    pub fullscreen_mode: u8,
+    pub visible: Option<bool>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct WindowProperties {
+    pub class: Option<String>,
+}
+
+/// From `GET_WORKSPACES`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct SwayWorkspace {
+    num: i64,
+    name: String,
+    focused: bool,
+    output: String,
+}
+
+/// From the `window` subscription
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct WindowEvent {
+    /// `new`, `close`, `focus`, `title`, `move`, `floating`, …
+    change: String,
+    container: Node,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct CommandReply {
+    success: bool,
+    error: Option<String>,
+}
+
+impl Node {
+    fn is_window(&self) -> bool {
+        matches!(self.kind.as_str(), "con" | "floating_con")
+            && self.nodes.is_empty()
+            && self.floating_nodes.is_empty()
+            && (self.app_id.is_some() || self.window.is_some())
+    }
+
+    /// Children, most recently focused first
+    fn children(&self) -> Vec<&Node> {
+        let mut children: Vec<&Node> = self.nodes.iter().chain(&self.floating_nodes).collect();
+        children.sort_by_key(|c| {
+            self.focus
+                .iter()
+                .position(|id| *id == c.id)
+                .unwrap_or(usize::MAX)
+        });
+        children
+    }
+
+    /// Appends the windows below this node, most recently focused first
+    fn windows(&self, workspace: &WorkspaceRef, monitor: i64, out: &mut Vec<Client>) {
+        if self.is_window() {
+            out.push(Client {
+                address: self.id.to_string(),
+                mapped: true,
+                hidden: self.visible == Some(false),
+                at: (self.rect.x, self.rect.y),
+                size: (self.rect.width, self.rect.height),
+                workspace: workspace.clone(),
+                floating: self.kind == "floating_con",
+                monitor,
+                class: self
+                    .app_id
+                    .clone()
+                    .or_else(|| self.window_properties.as_ref()?.class.clone())
+                    .unwrap_or_default(),
+                title: self.name.clone().unwrap_or_default(),
+                pid: self.pid,
+                xwayland: self.window.is_some(),
+                pinned: self.sticky,
+                fullscreen: if self.fullscreen_mode == 0 {
+                    Fullscreen::None
+                } else {
+                    Fullscreen::Fullscreen
+                },
+                // Set from `focused` once all windows are known
+                focus_history_id: if self.focused { 0 } else { -1 },
+                ..Client::default()
+            });
+            return;
+        }
+        for child in self.children() {
+            let workspace = if child.kind == "workspace" {
+                workspace_ref(child)
+            } else {
+                workspace.clone()
+            };
+            child.windows(&workspace, monitor, out);
+        }
+    }
+}
+
+fn workspace_ref(node: &Node) -> WorkspaceRef {
+    let name = node.name.clone().unwrap_or_default();
+    if name == SCRATCH_WORKSPACE {
+        return WorkspaceRef {
+            id: -1,
+            name: SCRATCHPAD.to_string(),
+        };
+    }
+    WorkspaceRef {
+        id: node.num.unwrap_or(-1),
+        name,
+    }
+}
+
+/// The windows of a layout tree, with their focus history
+#[must_use]
+pub fn tree_clients(root: &Node) -> Vec<Client> {
+    let mut clients = Vec::new();
+    for output in root.children() {
+        output.windows(
+            &WorkspaceRef::default(),
+            output_index(root, output.id),
+            &mut clients,
+        );
+    }
+    // The focused window may not be first when the focus is on an empty workspace,
+    // then no window gets 0
+    clients.sort_by_key(|c| c.focus_history_id != 0);
+    let offset = i32::from(clients.first().is_none_or(|c| c.focus_history_id != 0));
+    for (history, client) in (offset..).zip(clients.iter_mut()) {
+        client.focus_history_id = history;
+    }
+    clients
+}
+
+/// Outputs are numbered in layout order, the scratchpad output `__i3` is -1
+fn output_index(root: &Node, id: i64) -> i64 {
+    (0_i64..)
+        .zip(
+            root.nodes
+                .iter()
+                .filter(|o| o.name.as_deref() != Some("__i3")),
+        )
+        .find(|(_, o)| o.id == id)
+        .map_or(-1, |(i, _)| i)
+}
+
+fn send(stream: &mut UnixStream, kind: u32, payload: &str) -> Result<()> {
+    let len = u32::try_from(payload.len()).context("Sway request too long")?;
+    let mut message = MAGIC.to_vec();
+    message.extend(len.to_ne_bytes());
+    message.extend(kind.to_ne_bytes());
+    message.extend(payload.as_bytes());
+    stream
+        .write_all(&message)
+        .context("Failed to send Sway request")
+}
+
+/// Reads a reply or an event, as its type and payload
+fn receive(stream: &mut UnixStream) -> Result<(u32, String)> {
+    let mut header = [0; 14];
+    stream
+        .read_exact(&mut header)
+        .context("Failed to read Sway reply")?;
+    if &header[..6] != MAGIC {
+        bail!("Not an i3 IPC reply");
+    }
+    let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+    let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+    let mut payload = vec![0; len as usize];
+    stream
+        .read_exact(&mut payload)
+        .context("Failed to read Sway reply")?;
+    Ok((
+        kind,
+        String::from_utf8(payload).context("Sway reply is not UTF-8")?,
+    ))
+}
+
+/// A workspace as i3 commands take it, from a dispatcher argument (id or `name:`)
+fn target(workspace: &str) -> String {
+    if let Some(name) = workspace.strip_prefix("name:") {
+        quote(name)
+    } else if let Ok(num) = workspace.parse::<i64>() {
+        format!("number {num}")
+    } else {
+        quote(workspace)
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Client for the i3 IPC socket, one connection per request
+#[derive(Clone, Debug)]
+pub struct Sway {
+    socket: PathBuf,
+}
+
+impl Sway {
+    /// The IPC socket at `socket`, a fake server can listen there
+    #[must_use]
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+        }
+    }
+
+    fn connect(&self) -> Result<UnixStream> {
+        let stream = UnixStream::connect(&self.socket)
+            .with_context(|| format!("Failed to connect to {}", self.socket.display()))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        Ok(stream)
+    }
+
+    /// Sends a message and returns the payload of the reply
+    ///
+    /// # Errors
+    /// Returns an error if the socket can not be reached or the reply is malformed.
+    pub fn request(&self, kind: u32, payload: &str) -> Result<String> {
+        let mut stream = self.connect()?;
+        send(&mut stream, kind, payload)?;
+        Ok(receive(&mut stream)?.1)
+    }
+
+    fn get<T: DeserializeOwned>(&self, kind: u32) -> Result<T> {
+        let reply = self.request(kind, "")?;
+        serde_json::from_str(&reply).context("Failed to parse Sway reply")
+    }
+
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn tree(&self) -> Result<Node> {
+        self.get(GET_TREE)
+    }
+
+    /// Runs `;` separated commands, failing if any of them does
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or a command is rejected.
+    pub fn run(&self, commands: &[String]) -> Result<()> {
+        let reply = self.request(RUN_COMMAND, &commands.join("; "))?;
+        let replies: Vec<CommandReply> =
+            serde_json::from_str(&reply).context("Failed to parse Sway command reply")?;
+        if replies.len() != commands.len() {
+            bail!("Unexpected Sway command reply: {}", reply.trim());
+        }
+        commands
+            .iter()
+            .zip(replies)
+            .try_for_each(|(command, reply)| {
+                if reply.success {
+                    Ok(())
+                } else {
+                    bail!(
+                        "Sway rejected `{command}`: {}",
+                        reply.error.unwrap_or_default()
+                    )
+                }
+            })
+    }
+
+    /// Translates a window event, new and moved windows are looked up for their workspace
+    fn event(&self, event: WindowEvent) -> Option<Event> {
+        let address = event.container.id.to_string();
+        let workspace = || {
+            self.clients()
+                .ok()?
+                .into_iter()
+                .find(|c| c.address == address)
+                .map(|c| c.workspace)
+        };
+        Some(match event.change.as_str() {
+            "new" => Event::OpenWindow {
+                workspace: workspace().unwrap_or_default(),
+                class: event
+                    .container
+                    .app_id
+                    .clone()
+                    .or_else(|| event.container.window_properties.clone()?.class)
+                    .unwrap_or_default(),
+                title: event.container.name.clone().unwrap_or_default(),
+                address,
+            },
+            "close" => Event::CloseWindow { address },
+            "title" => Event::WindowTitle {
+                title: event.container.name.unwrap_or_default(),
+                address,
+            },
+            "move" => Event::MoveWindow {
+                workspace: workspace()?,
+                address,
+            },
+            "floating" => Event::ChangeFloatingMode {
+                floating: event.container.kind == "floating_con",
+                address,
+            },
+            _ => return None,
+        })
+    }
+
+    /// The commands doing `dispatch`
+    fn commands(dispatch: &Dispatch) -> Result<Vec<String>> {
+        let con = |address: &str| format!("[con_id={address}]");
+        Ok(match dispatch {
+            Dispatch::FocusWindow { address } => vec![format!("{} focus", con(address))],
+            Dispatch::MoveToWorkspaceSilent { workspace, address }
+                if workspace.starts_with("special") =>
+            {
+                vec![format!("{} move scratchpad", con(address))]
+            }
+            Dispatch::MoveToWorkspaceSilent { workspace, address } => vec![format!(
+                "{} move container to workspace {}",
+                con(address),
+                target(workspace)
+            )],
+            Dispatch::MoveToWorkspace { workspace } => vec![
+                format!("move container to workspace {}", target(workspace)),
+                format!("workspace {}", target(workspace)),
+            ],
+            Dispatch::Workspace { workspace } => vec![format!("workspace {}", target(workspace))],
+            Dispatch::ToggleSpecialWorkspace { .. } => vec![String::from("scratchpad show")],
+            Dispatch::RenameWorkspace { id, name } => {
+                vec![format!("rename workspace number {id} to {}", quote(name))]
+            }
+            Dispatch::CloseWindow { address } | Dispatch::KillWindow { address } => {
+                vec![format!("{} kill", con(address))]
+            }
+            Dispatch::ToggleFloating { address } => {
+                vec![format!("{} floating toggle", con(address))]
+            }
+            Dispatch::Pin { address } => vec![format!("{} sticky toggle", con(address))],
+            Dispatch::Fullscreen => vec![String::from("fullscreen toggle")],
+            Dispatch::CenterWindow => vec![String::from("move position center")],
+            Dispatch::FocusMonitor { monitor } => vec![format!("focus output {}", quote(monitor))],
+            Dispatch::MoveCurrentWorkspaceToMonitor { monitor } => {
+                vec![format!("move workspace to output {}", quote(monitor))]
+            }
+            Dispatch::ToggleGroup
+            | Dispatch::MoveIntoGroup { .. }
+            | Dispatch::ToggleDpms { .. }
+            | Dispatch::Raw { .. } => {
+                bail!("`{dispatch}` is not supported on Sway")
+            }
+        })
+    }
+}
+
+impl Compositor for Sway {
+    fn name(&self) -> &'static str {
+        "Sway"
+    }
+
+    fn clients(&self) -> Result<Vec<Client>> {
+        Ok(tree_clients(&self.tree()?))
+    }
+
+    fn active_workspace(&self) -> Result<Workspace> {
+        let workspaces: Vec<SwayWorkspace> = self.get(GET_WORKSPACES)?;
+        let active = workspaces
+            .into_iter()
+            .find(|w| w.focused)
+            .context("Sway reports no focused workspace")?;
+        Ok(Workspace {
+            id: active.num,
+            name: active.name,
+            monitor: active.output,
+            ..Workspace::default()
+        })
+    }
+
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        let tree = self.tree()?;
+        Ok(tree
+            .nodes
+            .iter()
+            .filter(|o| o.name.as_deref() != Some("__i3"))
+            .map(|o| Monitor {
+                id: output_index(&tree, o.id),
+                name: o.name.clone().unwrap_or_default(),
+                width: o.rect.width.try_into().unwrap_or_default(),
+                height: o.rect.height.try_into().unwrap_or_default(),
+                x: o.rect.x,
+                y: o.rect.y,
+                focused: tree.focus.first() == Some(&o.id),
+                ..Monitor::default()
+            })
+            .collect())
+    }
+
+    fn stash_workspace(&self, _stash: &str) -> String {
+        SCRATCHPAD.to_string()
+    }
+
+    fn events(&self) -> Result<Box<dyn Iterator<Item = Event> + Send>> {
+        let mut stream = self.connect()?;
+        send(&mut stream, SUBSCRIBE, r#"["window"]"#)?;
+        let (_, reply) = receive(&mut stream)?;
+        let reply: CommandReply =
+            serde_json::from_str(&reply).context("Failed to parse Sway subscribe reply")?;
+        if !reply.success {
+            bail!("Sway refused the window events subscription");
+        }
+        // Events come whenever windows change
+        stream.set_read_timeout(None)?;
+        let sway = self.clone();
+        Ok(Box::new(std::iter::from_fn(move || {
+            loop {
+                let (kind, payload) = receive(&mut stream).ok()?;
+                if kind != WINDOW_EVENT {
+                    continue;
+                }
+                if let Some(event) = serde_json::from_str(&payload)
+                    .ok()
+                    .and_then(|e| sway.event(e))
+                {
+                    return Some(event);
+                }
+            }
+        })))
+    }
+
+    fn batch(&self, dispatches: &[Dispatch]) -> Result<()> {
+        if dispatches.is_empty() {
+            return Ok(());
+        }
+        let commands = dispatches
+            .iter()
+            .map(Self::commands)
+            .collect::<Result<Vec<_>>>()?
+            .concat();
+        self.run(&commands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::net::UnixListener, thread::JoinHandle};
+
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!(
+            "{}/tests/fixtures/sway/{name}.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"))
+    }
+
+    /// A fake Sway answering each connection with the next messages of `replies`.
+    /// Joining the handle returns the requests it got, as their type and payload.
+    fn serve(
+        replies: Vec<Vec<(u32, String)>>,
+    ) -> (tempfile::TempDir, Sway, JoinHandle<Vec<(u32, String)>>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sway-ipc.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            replies
+                .into_iter()
+                .map(|messages| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let request = receive(&mut stream).unwrap();
+                    for (kind, payload) in messages {
+                        send(&mut stream, kind, &payload).unwrap();
+                    }
+                    request
+                })
+                .collect()
+        });
+        (dir, Sway::new(path), server)
+    }
+
+    fn reply(kind: u32, payload: &str) -> Vec<(u32, String)> {
+        vec![(kind, payload.to_string())]
+    }
+
+    fn workspace(id: i64, name: &str) -> WorkspaceRef {
+        WorkspaceRef {
+            id,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn clients_from_tree() {
+        let (_dir, sway, server) = serve(vec![reply(GET_TREE, &fixture("tree"))]);
+        let clients = sway.clients().unwrap();
+        let summary: Vec<_> = clients
+            .iter()
+            .map(|c| {
+                (
+                    c.address.as_str(),
+                    c.class.as_str(),
+                    c.focus_history_id,
+                    c.workspace.selector(),
+                    c.monitor,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("11", "XTerm", 0, "1".to_string(), 0),
+                ("10", "firefox", 1, "1".to_string(), 0),
+                ("12", "thunderbird", 2, "name:mail".to_string(), 0),
+                ("13", "mpv", 3, "2".to_string(), 1),
+                (
+                    "14",
+                    "org.keepassxc.KeePassXC",
+                    4,
+                    SCRATCHPAD.to_string(),
+                    -1
+                ),
+            ]
+        );
+        let xterm = &clients[0];
+        assert!(xterm.xwayland);
+        assert_eq!(xterm.title, "vim");
+        assert_eq!((xterm.at, xterm.size), ((1280, 0), (1280, 1440)));
+        assert_eq!(clients[2].fullscreen, Fullscreen::Fullscreen);
+        assert!(clients[3].floating && clients[3].pinned);
+        assert!(clients[4].hidden);
+        assert_eq!(server.join().unwrap(), [(GET_TREE, String::new())]);
+    }
+
+    #[test]
+    fn workspaces_and_monitors() {
+        let (_dir, sway, server) = serve(vec![
+            reply(GET_WORKSPACES, &fixture("workspaces")),
+            reply(GET_TREE, &fixture("tree")),
+        ]);
+        let active = sway.active_workspace().unwrap();
+        assert_eq!((active.id, active.name.as_str()), (1, "1"));
+        assert_eq!(active.monitor, "DP-1");
+        let monitors: Vec<_> = sway
+            .monitors()
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.id, m.name, m.width, m.x, m.focused))
+            .collect();
+        assert_eq!(
+            monitors,
+            [
+                (0, "DP-1".to_string(), 2560, 0, true),
+                (1, "HDMI-A-1".to_string(), 1920, 2560, false),
+            ]
+        );
+        let kinds: Vec<u32> = server.join().unwrap().into_iter().map(|r| r.0).collect();
+        assert_eq!(kinds, [GET_WORKSPACES, GET_TREE]);
+    }
+
+    #[test]
+    fn commands() {
+        let (_dir, sway, server) = serve(vec![
+            reply(RUN_COMMAND, r#"[{"success":true},{"success":true}]"#),
+            reply(
+                RUN_COMMAND,
+                r#"[{"success":true},{"success":false,"parse_error":false,"error":"No matching node."}]"#,
+            ),
+        ]);
+        sway.batch(&[
+            Dispatch::MoveToWorkspaceSilent {
+                workspace: String::from("name:mail"),
+                address: String::from("10"),
+            },
+            Dispatch::FocusWindow {
+                address: String::from("10"),
+            },
+        ])
+        .unwrap();
+        let err = sway
+            .batch(&[
+                Dispatch::MoveToWorkspaceSilent {
+                    workspace: String::from("special:scratchpad"),
+                    address: String::from("10"),
+                },
+                Dispatch::CloseWindow {
+                    address: String::from("99"),
+                },
+            ])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Sway rejected `[con_id=99] kill`: No matching node."
+        );
+        // Unsupported dispatchers fail before anything is sent
+        assert!(sway.dispatch(&Dispatch::ToggleGroup).is_err());
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                (
+                    RUN_COMMAND,
+                    String::from(
+                        r#"[con_id=10] move container to workspace "mail"; [con_id=10] focus"#
+                    )
+                ),
+                (
+                    RUN_COMMAND,
+                    String::from("[con_id=10] move scratchpad; [con_id=99] kill")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_events() {
+        let event = |change: &str, container: &str| {
+            (
+                WINDOW_EVENT,
+                format!(r#"{{"change":"{change}","container":{container}}}"#),
+            )
+        };
+        let (_dir, sway, server) = serve(vec![
+            vec![
+                (SUBSCRIBE, String::from(r#"{"success":true}"#)),
+                // A workspace event, skipped
+                (0x8000_0000, String::from(r#"{"change":"focus"}"#)),
+                event(
+                    "new",
+                    r#"{"id":10,"type":"con","name":"Mozilla Firefox","app_id":"firefox"}"#,
+                ),
+                event("move", r#"{"id":12,"type":"con","app_id":"thunderbird"}"#),
+                event("focus", r#"{"id":10,"type":"con"}"#),
+                event("title", r#"{"id":11,"type":"con","name":"vim README.md"}"#),
+                event("floating", r#"{"id":11,"type":"floating_con"}"#),
+                event("close", r#"{"id":13,"type":"floating_con"}"#),
+            ],
+            // Looked up for the workspace of the new and moved windows
+            reply(GET_TREE, &fixture("tree")),
+            reply(GET_TREE, &fixture("tree")),
+        ]);
+        let events: Vec<Event> = sway.events().unwrap().collect();
+        assert_eq!(
+            events,
+            [
+                Event::OpenWindow {
+                    address: String::from("10"),
+                    workspace: workspace(1, "1"),
+                    class: String::from("firefox"),
+                    title: String::from("Mozilla Firefox"),
+                },
+                Event::MoveWindow {
+                    address: String::from("12"),
+                    workspace: workspace(-1, "mail"),
+                },
+                Event::WindowTitle {
+                    address: String::from("11"),
+                    title: String::from("vim README.md"),
+                },
+                Event::ChangeFloatingMode {
+                    address: String::from("11"),
+                    floating: true,
+                },
+                Event::CloseWindow {
+                    address: String::from("13"),
+                },
+            ]
+        );
+        assert_eq!(
+            server.join().unwrap()[0],
+            (SUBSCRIBE, String::from(r#"["window"]"#))
+        );
+    }
+
+    #[test]
+    fn unreachable_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = Sway::new(dir.path().join("none.sock"))
+            .clients()
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Failed to connect to"));
+    }
+}
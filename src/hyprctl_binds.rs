@@ -0,0 +1,142 @@
+use std::{borrow::Cow, sync::Arc};
+
+use anyhow::{Context as _, Result, bail};
+use skim::{ItemPreview, PreviewContext, SkimItem, SkimOutput};
+
+use crate::{
+    SkimRun,
+    hyprland::{Bind, Dispatch, Hyprland},
+};
+
+/// `SkimItem` wrapper for a Hyprland bind
+struct BindItem {
+    bind: Bind,
+}
+
+impl SkimItem for BindItem {
+    fn text(&self) -> Cow<'_, str> {
+        let b = &self.bind;
+        let submap = if b.submap.is_empty() {
+            String::new()
+        } else {
+            format!("[{}] ", b.submap)
+        };
+        let description = if b.has_description {
+            format!(" - {}", b.description)
+        } else {
+            String::new()
+        };
+        let action = if b.arg.is_empty() {
+            b.dispatcher.clone()
+        } else {
+            format!("{} {}", b.dispatcher, b.arg)
+        };
+        Cow::Owned(format!("{submap}{} → {action}{description}", b.combo()))
+    }
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        let b = &self.bind;
+        let flags: Vec<&str> = [
+            (b.locked, "locked"),
+            (b.repeat, "repeat"),
+            (b.release, "release"),
+            (b.mouse, "mouse"),
+            (b.long_press, "long press"),
+            (b.non_consuming, "non-consuming"),
+            (b.catch_all, "catch-all"),
+        ]
+        .into_iter()
+        .filter_map(|(on, flag)| on.then_some(flag))
+        .collect();
+        ItemPreview::Text(format!(
+            "Keys: {}\nDispatcher: {}\nArgument: {}\nFlags: {}\nSubmap: {}\nKey: {} (code {})\nModmask: {}\nDescription: {}",
+            b.combo(),
+            b.dispatcher,
+            b.arg,
+            if flags.is_empty() {
+                String::from("none")
+            } else {
+                flags.join(", ")
+            },
+            if b.submap.is_empty() {
+                "global"
+            } else {
+                &b.submap
+            },
+            b.key,
+            b.keycode,
+            b.modmask,
+            b.description
+        ))
+    }
+}
+
+/// Mode listing the Hyprland binds, running the dispatcher of the selected one (enter).
+/// Dispatchers acting on the focused window would target the picker itself when it has
+/// `ignore_class`, the previously focused window gets the focus back first then.
+pub struct HyprctlBinds {
+    pub ignore_class: Option<String>,
+}
+
+impl SkimRun for HyprctlBinds {
+    fn get(&self) -> Vec<Arc<dyn SkimItem>> {
+        let hypr = Hyprland::from_env().expect("Failed to connect to hyprland");
+        let mut binds = hypr.binds().expect("Failed to get hyprland binds");
+        // Global binds first, then by submap
+        binds.sort_by(|a, b| a.submap.cmp(&b.submap));
+        binds
+            .into_iter()
+            .map(|bind| Arc::new(BindItem { bind }) as Arc<dyn SkimItem>)
+            .collect()
+    }
+
+    fn set_options(&self, opts: &mut skim::SkimOptions) {
+        opts.preview = Some(String::new());
+        opts.preview_window = String::from("up:40%");
+        opts.header = Some("Enter: run the dispatcher of the bind.".to_string());
+    }
+
+    fn run(&self, output: &SkimOutput) -> Result<()> {
+        let bind = &output
+            .selected_items
+            .first()
+            .context("No bind selected")?
+            .as_any()
+            .downcast_ref::<BindItem>()
+            .context("Unexpected item")?
+            .bind;
+        if bind.mouse {
+            bail!("Mouse binds act while dragging and can not be run from here");
+        }
+        let hypr = Hyprland::from_env()?;
+        if let Some(previous) = self.previous_window(&hypr)? {
+            hypr.dispatch(&Dispatch::FocusWindow { address: previous })?;
+        }
+        // Not batched, arguments like those of `exec` may hold the `;` separating batched commands
+        hypr.dispatch(&Dispatch::Raw {
+            dispatcher: bind.dispatcher.clone(),
+            arg: bind.arg.clone(),
+        })
+        .with_context(|| format!("Failed to run the bind {}", bind.combo()))
+    }
+}
+
+impl HyprctlBinds {
+    /// The window focused before the picker, when the focused window has the ignored class
+    fn previous_window(&self, hypr: &Hyprland) -> Result<Option<String>> {
+        let Some(ref ignore) = self.ignore_class else {
+            return Ok(None);
+        };
+        if hypr
+            .active_window()?
+            .is_none_or(|active| active.class != *ignore)
+        {
+            return Ok(None);
+        }
+        Ok(hypr
+            .clients()?
+            .into_iter()
+            .filter(|c| c.class != *ignore && c.focus_history_id > 0)
+            .min_by_key(|c| c.focus_history_id)
+            .map(|c| c.address))
+    }
+}
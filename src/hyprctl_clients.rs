@@ -1,63 +1,342 @@
-use std::{borrow::Cow, process::Command, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
-use anyhow::Context as _;
-use serde::Deserialize;
-use skim::SkimItem;
+use anyhow::{Context as _, Result, bail};
+use skim::{ItemPreview, PreviewContext, SkimItem, prelude::SkimItemSender};
 
-use crate::SkimRun;
+use crate::{
+    SkimRun,
+    compositor::{self, Compositor},
+    hyprctl_hide::DEFAULT_STASH,
+    hyprland::{
+        Client, Dispatch,
+        live::{self, LiveClient},
+        model::Fullscreen,
+    },
+};
 
-#[derive(Deserialize)]
-#[allow(non_snake_case)]
-struct Client {
-    title: String,
-    focusHistoryID: u16,
-    address: String,
+/// Actions on the selected window, besides focusing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum WindowAction {
+    Close,
+    Kill,
+    Float,
+    Fullscreen,
+    Pin,
+    /// Move the window to the current workspace and focus it
+    Pull,
+    /// Move the window to the default stash of `hyprctl-hide`
+    Hide,
+    Center,
+    /// Group the window with the one focused before the picker
+    Group,
 }
 
-pub struct HyprctlClients;
+/// Line of each window, see [`render`] for the fields
+pub static DEFAULT_FORMAT: &str = "{title} [{class}] @{workspace}{state}";
+
+/// Sorts the windows by this and prefixes their line with it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum GroupBy {
+    Workspace,
+    Monitor,
+}
+
+/// `SkimItem` wrapper for a Hyprland client, updated while skim runs.
+struct Window {
+    client: LiveClient,
+    view: Arc<View>,
+}
+
+/// How the windows are displayed, shared by all items
+struct View {
+    format: String,
+    group_by: Option<GroupBy>,
+    /// Monitor names by id
+    monitors: HashMap<i64, String>,
+}
+
+pub struct HyprctlClients {
+    pub format: String,
+    /// List the focused window too
+    pub include_current: bool,
+    pub group_by: Option<GroupBy>,
+}
 
 impl SkimRun for HyprctlClients {
-    fn get(&self) -> Vec<std::sync::Arc<dyn SkimItem>> {
-        let res = Command::new("hyprctl")
-            .arg("clients")
-            .arg("-j")
-            .output()
-            .expect("Failed to get hyprcl clients")
-            .stdout;
-        let mut clients: Vec<Client> =
-            serde_json::from_slice(&res).expect("Failed to parse clients from JSON");
-        clients.sort_unstable_by(|a, b| a.focusHistoryID.cmp(&b.focusHistoryID));
-        clients
-            .into_iter()
-            .filter_map(|c| {
-                if c.focusHistoryID == 0 {
-                    return None;
-                }
-                Some(Arc::new(c) as Arc<dyn SkimItem>)
-            })
-            .collect()
+    fn init(&self, mode: &crate::Mode) -> bool {
+        if let crate::Mode::HyprctlClients {
+            action: Some(action),
+            address: Some(address),
+            ..
+        } = mode
+        {
+            if let Err(e) = compositor::from_env().and_then(|wm| act(&*wm, *action, address)) {
+                eprintln!("[hyprctl-clients] {e:#}");
+            }
+            return false;
+        }
+        true
+    }
+
+    fn stream(&self, tx: SkimItemSender) {
+        let wm = compositor::from_env().expect("Failed to connect to the compositor");
+        let mut clients = wm.clients().expect("Failed to get the windows");
+        clients.sort_unstable_by_key(|c| c.focus_history_id);
+        // Stable, windows stay by recency within a group
+        match self.group_by {
+            Some(GroupBy::Workspace) => {
+                clients.sort_by_key(|c| (c.workspace.is_special(), c.workspace.id));
+            }
+            Some(GroupBy::Monitor) => clients.sort_by_key(|c| c.monitor),
+            None => {}
+        }
+        // Skip the focused window
+        let active = clients
+            .iter()
+            .find(|c| c.focus_history_id == 0 && !self.include_current)
+            .map(|c| c.address.clone())
+            .unwrap_or_default();
+        let view = Arc::new(View {
+            format: self.format.clone(),
+            group_by: self.group_by,
+            monitors: wm
+                .monitors()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|m| (m.id, m.name))
+                .collect(),
+        });
+        live::stream(
+            &*wm,
+            clients,
+            tx,
+            move |c| c.address != active,
+            move |client| {
+                Arc::new(Window {
+                    client,
+                    view: view.clone(),
+                })
+            },
+        );
     }
-    fn run(&self, output: &skim::SkimOutput) -> anyhow::Result<()> {
+
+    fn set_options(&self, opts: &mut skim::SkimOptions) {
+        let exe = crate::current_exe();
+        // Actions that leave the focus alone keep the picker open, the list follows the changes
+        let stay = |key: &str, action: &str| {
+            format!("{key}:execute-silent({exe} hyprctl-clients --action {action} --address {{}})")
+        };
+        let leave = |key: &str, action: &str| {
+            format!("{key}:accept(hyprctl-clients --action {action} --address {{}})")
+        };
+        opts.preview = Some(String::new());
+        opts.preview_window = String::from("up:40%");
+        opts.bind.extend(vec![
+            stay("alt-c", "close"),
+            stay("alt-k", "kill"),
+            stay("alt-t", "float"),
+            stay("alt-p", "pin"),
+            stay("alt-s", "hide"),
+            leave("alt-m", "fullscreen"),
+            leave("alt-enter", "pull"),
+            leave("alt-x", "center"),
+            leave("alt-g", "group"),
+        ]);
+        // The workspace picker only runs on Hyprland
+        #[cfg(feature = "hyprland")]
+        let on_hyprland = compositor::from_env().is_ok_and(|wm| wm.name() == "Hyprland");
+        #[cfg(not(feature = "hyprland"))]
+        let on_hyprland = false;
+        if on_hyprland {
+            opts.bind
+                .push("alt-w:accept(hyprctl-workspaces --window {})".to_string());
+        }
+        opts.header = Some(format!(
+            "Enter: focus. Alt- c: close, k: kill, t: float, p: pin, s: hide, m: fullscreen, enter: pull, x: center, g: group{}",
+            if on_hyprland {
+                ", w: move to workspace"
+            } else {
+                ""
+            }
+        ));
+    }
+
+    fn run(&self, output: &skim::SkimOutput) -> Result<()> {
         let result = output
             .selected_items
             .first()
-            .expect("Failed to get selected item");
-        let _ = Command::new("hyprctl")
-            .arg("dispatch")
-            .arg("focuswindow")
-            .arg(format!("address:{}", result.output()))
-            .spawn()
-            .context("Failed to run hyprctl dispatch focuswindow")?
-            .wait();
-        Ok(())
+            .context("No window selected")?;
+        if result
+            .as_any()
+            .downcast_ref::<Window>()
+            .is_some_and(|w| w.client.is_closed())
+        {
+            bail!("The window was closed");
+        }
+        compositor::from_env()?
+            .dispatch(&Dispatch::FocusWindow {
+                address: result.output().to_string(),
+            })
+            .context("Failed to focus window")
     }
 }
 
-impl SkimItem for Client {
-    fn text(&self) -> std::borrow::Cow<str> {
-        Cow::Borrowed(&self.title)
+impl SkimItem for Window {
+    fn text(&self) -> Cow<'_, str> {
+        let client = self.client.client();
+        let monitor = self
+            .view
+            .monitors
+            .get(&client.monitor)
+            .cloned()
+            .unwrap_or_else(|| client.monitor.to_string());
+        let states = state_badges(&client);
+        let states = if states.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", states.join(", "))
+        };
+        let line = render(&self.view.format, |field| {
+            Some(match field {
+                "title" => client.title.clone(),
+                "class" => client.class.clone(),
+                "workspace" => client.workspace.name.clone(),
+                "monitor" => monitor.clone(),
+                "address" => client.address.clone(),
+                "pid" => client.pid.to_string(),
+                "initial_title" => client.initial_title.clone(),
+                "initial_class" => client.initial_class.clone(),
+                "state" => states.clone(),
+                _ => return None,
+            })
+        });
+        // Skim cannot drop an item, a closed window stays listed whatever the format
+        let closed = if self.client.is_closed() {
+            " (closed)"
+        } else {
+            ""
+        };
+        Cow::Owned(match self.view.group_by {
+            Some(GroupBy::Workspace) => format!("{}: {line}{closed}", client.workspace.name),
+            Some(GroupBy::Monitor) => format!("{monitor}: {line}{closed}"),
+            None => format!("{line}{closed}"),
+        })
+    }
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Owned(self.client.client().address)
+    }
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        ItemPreview::Text(self.client.client().properties())
+    }
+}
+
+/// Replaces the `{field}`s of `template` with their value, unknown fields are kept as is
+fn render(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        match value(&rest[1..end]) {
+            Some(v) => out.push_str(&v),
+            None => out.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn state_badges(client: &Client) -> Vec<&'static str> {
+    [
+        (client.floating, "floating"),
+        (client.pinned, "pinned"),
+        (client.fullscreen != Fullscreen::None, "fullscreen"),
+        (!client.grouped.is_empty(), "grouped"),
+        (client.hidden, "hidden"),
+    ]
+    .into_iter()
+    .filter_map(|(on, badge)| on.then_some(badge))
+    .collect()
+}
+
+/// Runs `action` on the window at `address`
+fn act(wm: &dyn Compositor, action: WindowAction, address: &str) -> Result<()> {
+    let address = address.to_string();
+    let focus = Dispatch::FocusWindow {
+        address: address.clone(),
+    };
+    match action {
+        WindowAction::Close => wm.dispatch(&Dispatch::CloseWindow { address }),
+        WindowAction::Kill => wm.dispatch(&Dispatch::KillWindow { address }),
+        WindowAction::Float => wm.dispatch(&Dispatch::ToggleFloating { address }),
+        WindowAction::Pin => wm.dispatch(&Dispatch::Pin { address }),
+        WindowAction::Hide => wm.dispatch(&Dispatch::MoveToWorkspaceSilent {
+            workspace: wm.stash_workspace(DEFAULT_STASH),
+            address,
+        }),
+        // Only apply to the focused window
+        WindowAction::Fullscreen => wm.batch(&[focus, Dispatch::Fullscreen]),
+        WindowAction::Center => wm.batch(&[focus, Dispatch::CenterWindow]),
+        WindowAction::Pull => {
+            let workspace = wm.active_workspace()?.selector();
+            wm.batch(&[
+                Dispatch::MoveToWorkspaceSilent { workspace, address },
+                focus,
+            ])
+        }
+        WindowAction::Group => group_with_previous(wm, &address),
+    }
+    .with_context(|| format!("Failed to run {action:?} on the window"))
+}
+
+/// Moves the window at `address` into the group of the window focused before the picker,
+/// which becomes a group first if needed
+fn group_with_previous(wm: &dyn Compositor, address: &str) -> Result<()> {
+    let active = wm
+        .previous_window()?
+        .context("No window was focused before the picker")?;
+    if active.address == address {
+        bail!("The window is already the previously focused one");
     }
-    fn output(&self) -> std::borrow::Cow<str> {
-        Cow::Borrowed(&self.address)
+    // `togglegroup` acts on the focused window, which is the picker for now
+    let mut dispatches = vec![Dispatch::FocusWindow {
+        address: active.address.clone(),
+    }];
+    if active.grouped.is_empty() {
+        dispatches.push(Dispatch::ToggleGroup);
     }
+    dispatches.extend([
+        Dispatch::MoveToWorkspaceSilent {
+            workspace: active.workspace.selector(),
+            address: address.to_string(),
+        },
+        Dispatch::FocusWindow {
+            address: address.to_string(),
+        },
+    ]);
+    wm.batch(&dispatches)?;
+
+    // `moveintogroup` takes a direction, the window was laid out next to the group by now
+    let clients = wm.clients()?;
+    let find = |address: &str| {
+        clients
+            .iter()
+            .find(|c| c.address == address)
+            .with_context(|| format!("Window {address} disappeared"))
+    };
+    let (window, group) = (find(address)?, find(&active.address)?);
+    let center = |c: &Client| (c.at.0 + c.size.0 / 2, c.at.1 + c.size.1 / 2);
+    let ((wx, wy), (gx, gy)) = (center(window), center(group));
+    let direction = if (gx - wx).abs() >= (gy - wy).abs() {
+        if gx < wx { 'l' } else { 'r' }
+    } else if gy < wy {
+        'u'
+    } else {
+        'd'
+    };
+    wm.dispatch(&Dispatch::MoveIntoGroup { direction })
 }
@@ -1,104 +1,125 @@
-use std::{borrow::Cow, process::Command, sync::Arc};
-
-use anyhow::Result;
-use serde::Deserialize;
-use skim::{ItemPreview, PreviewContext, SkimItem, SkimOutput};
-
-use crate::SkimRun;
-
-/// Represents a Hyprland client/window.
-#[derive(Deserialize, Debug)]
-#[allow(non_snake_case)]
-struct Client {
-    address: String,
-    class: String,
-    title: String,
-    hidden: bool,
-    mapped: bool,
-    focusHistoryID: u16,
-    workspace: Workspace,
-}
+use std::{borrow::Cow, sync::Arc};
+
+use anyhow::{Context as _, Result, bail};
+use skim::{ItemPreview, PreviewContext, SkimItem, SkimOutput, prelude::SkimItemSender};
+
+use crate::{
+    SkimRun,
+    compositor::{self, Compositor, StashPredicate},
+    hyprland::{
+        Client, Dispatch, WorkspaceRef,
+        live::{self, LiveClient},
+    },
+};
+
+/// Stash used when `--stash` is not given
+pub static DEFAULT_STASH: &str = "hidden";
 
-#[derive(Deserialize, Debug)]
-struct Workspace {
-    name: String,
+/// The special workspace holding the windows of a stash
+#[must_use]
+pub fn stash_workspace(stash: &str) -> String {
+    format!("special:{stash}")
 }
 
-/// `SkimItem` wrapper for a Hyprland client.
+/// `SkimItem` wrapper for a Hyprland client, updated while skim runs.
 struct ClassWindow {
-    client: Client,
+    client: LiveClient,
+    /// The stash listed, `None` when listing all of them
+    stash: Option<String>,
+    /// Whether a workspace is a stash, from the compositor
+    is_stash: StashPredicate,
 }
 
 impl SkimItem for ClassWindow {
-    fn text(&self) -> Cow<str> {
+    fn text(&self) -> Cow<'_, str> {
+        let client = self.client.client();
+        let stashed = match &self.stash {
+            Some(stash) => client.workspace.name == *stash,
+            None => (self.is_stash)(&client.workspace),
+        };
+        // Unhidden or closed from elsewhere while the picker is up, skim cannot drop the item
+        let state = match (self.client.is_closed(), stashed) {
+            (true, _) => " (closed)",
+            (false, true) => "",
+            (false, false) => " (unhidden)",
+        };
+        let prefix = if self.stash.is_none() && stashed {
+            format!("{}: ", stash_name(&client.workspace.name))
+        } else {
+            String::new()
+        };
         Cow::Owned(format!(
-            "{} [{}]{}{}",
-            self.client.title,
-            self.client.class,
-            if self.client.hidden { " (hidden)" } else { "" },
-            if self.client.mapped {
-                ""
-            } else {
-                " (unmapped)"
-            }
+            "{prefix}{} [{}]{}{}{}",
+            client.title,
+            client.class,
+            if client.hidden { " (hidden)" } else { "" },
+            if client.mapped { "" } else { " (unmapped)" },
+            state
         ))
     }
-    fn output(&self) -> Cow<str> {
-        Cow::Borrowed(&self.client.address)
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Owned(self.client.client().address)
     }
     fn preview(&self, _context: PreviewContext) -> ItemPreview {
-        ItemPreview::Text(format!(
-            "Title: {}\nClass: {}\nWorkspace: {}\nAddress: {}\nHidden: {}\nMapped: {}\nFocusHistoryID: {}",
-            self.client.title,
-            self.client.class,
-            self.client.workspace.name,
-            self.client.address,
-            self.client.hidden,
-            self.client.mapped,
-            self.client.focusHistoryID
-        ))
+        ItemPreview::Text(self.client.client().properties())
     }
 }
 
-/// Mode for listing all windows in the special hidden workspace (id 99).
+/// The stash of a special workspace, `special:work` is `work`
+fn stash_name(workspace: &str) -> &str {
+    workspace.strip_prefix("special:").unwrap_or(workspace)
+}
+
+/// Mode for listing the windows of a stash, a special workspace (`special:hidden` by default),
+/// or of all the stashes.
 /// Lets the user swap the selected hidden window with the current one (enter),
-/// or simply unhide it (shift-enter).
+/// simply unhide it (alt-enter) or restore its whole stash (alt-r).
 #[derive(Clone)]
 pub struct HyprctlHide {
     pub ignore_class: Option<String>,
-    pub swap: Option<String>,
+    pub stash: String,
+    /// List the windows of every special workspace
+    pub all: bool,
 }
 
 impl SkimRun for HyprctlHide {
-    fn get(&self) -> Vec<Arc<dyn SkimItem>> {
+    fn stream(&self, tx: SkimItemSender) {
         eprintln!("[hyprctl-hide][get] ignore_class: {:?}", self.ignore_class);
-        let res = Command::new("hyprctl")
-            .arg("clients")
-            .arg("-j")
-            .output()
-            .expect("Failed to get hyprctl clients")
-            .stdout;
-        let clients: Vec<Client> =
-            serde_json::from_slice(&res).expect("Failed to parse clients from JSON");
-
-        // List all windows in the special:hidden workspace, ignoring the specified class,
-        // and sort by recency (focusHistoryID ascending)
-        let mut filtered: Vec<Client> = clients
-            .into_iter()
-            .filter(|c| c.workspace.name == "special:hidden")
-            .filter(|c| {
-                if let Some(ref ignore) = self.ignore_class {
-                    c.class != *ignore
-                } else {
-                    true
-                }
-            })
-            .collect();
-        filtered.sort_by_key(|c| c.focusHistoryID);
-        filtered
-            .into_iter()
-            .map(|c| Arc::new(ClassWindow { client: c }) as Arc<dyn SkimItem>)
-            .collect()
+        let wm = compositor::from_env().expect("Failed to connect to the compositor");
+        let mut clients = wm.clients().expect("Failed to get the windows");
+        // Sort by recency (focusHistoryID ascending)
+        clients.sort_by_key(|c| c.focus_history_id);
+        if self.all {
+            // Stable, windows stay by recency within a stash
+            clients.sort_by(|a, b| a.workspace.name.cmp(&b.workspace.name));
+        }
+
+        // List the windows of the stash, ignoring the specified class,
+        // including those hidden from elsewhere while skim runs
+        let ignore_class = self.ignore_class.clone();
+        let stash = (!self.all).then(|| wm.stash_workspace(&self.stash));
+        let filter_stash = stash.clone();
+        let is_stash = wm.stash_predicate(&self.stash);
+        let filter_is_stash = is_stash.clone();
+        live::stream(
+            &*wm,
+            clients,
+            tx,
+            move |c| {
+                let stashed = match &filter_stash {
+                    Some(stash) => c.workspace.name == *stash,
+                    None => filter_is_stash(&c.workspace),
+                };
+                stashed && ignore_class.as_ref() != Some(&c.class)
+            },
+            move |client| {
+                Arc::new(ClassWindow {
+                    client,
+                    stash: stash.clone(),
+                    is_stash: is_stash.clone(),
+                })
+            },
+        );
     }
 
     fn set_options(&self, opts: &mut skim::SkimOptions) {
@@ -107,30 +128,42 @@ impl SkimRun for HyprctlHide {
             self.ignore_class
         );
         opts.preview = Some(String::new());
-        opts.header = Some("List of hidden windows (special:hidden). Enter: swap with current (or previously focused if ignored). Alt-Enter: unhide.".to_string());
+        let listed = if self.all {
+            String::from("all stashes")
+        } else {
+            stash_workspace(&self.stash)
+        };
+        opts.header = Some(format!(
+            "List of hidden windows ({listed}). Enter: swap with current (or previously focused if ignored). Alt-Enter: unhide. Alt-r: restore the whole stash."
+        ));
         opts.preview_window = String::from("up:40%");
 
         // The swap logic:
         // 1. Get the currently focused window's class.
         // 2. If it matches ignore_class, find the previously focused window (not ignored).
-        // 3. Hide that window instead.
+        // 3. Hide that window instead, into the stash of the selected window.
         // 4. Unhide (move to current workspace and focus) the selected window.
 
-        // This is implemented as a shell script in the execute binding.
+        // This is implemented by the `--swap` flag, run from the execute binding.
         let ignore_class_arg = if let Some(ref ignore) = self.ignore_class {
-            format!(" --ignore-class {}", ignore)
+            format!(" --ignore-class {ignore}")
         } else {
             String::new()
         };
         opts.bind.extend(vec![
             format!(
-                "enter:execute({} hyprctl-hide{} --swap {})+accept",
-                std::env::current_exe().map(|p| p.display().to_string()).unwrap_or_else(|_| "skim-run".to_string()),
+                "enter:execute({} hyprctl-hide{} --stash {} --swap {})+accept",
+                crate::current_exe(),
                 ignore_class_arg,
+                self.stash,
                 "{}"
             ),
             // Alt-Enter: unhide selected window (move to current workspace and focus)
-            "alt-enter:execute(hyprctl activeworkspace -j | jq -r .id | xargs -I{ws} hyprctl dispatch movetoworkspacesilent {ws},address:{} ; hyprctl dispatch focuswindow address:{})+accept".to_string(),
+            "alt-enter:accept(hyprctl-hide --unhide {})".to_string(),
+            format!(
+                "alt-r:accept(hyprctl-hide{ignore_class_arg} --stash {} --restore-with {{}})",
+                self.stash
+            ),
         ]);
     }
 
@@ -139,137 +172,457 @@ impl SkimRun for HyprctlHide {
         Ok(())
     }
     fn init(&self, mode: &crate::Mode) -> bool {
-        // If swap argument is provided, perform the swap logic and exit.
-        if let crate::Mode::HyprctlHide {
-            swap: Some(target_addr),
+        let crate::Mode::HyprctlHide {
+            swap,
+            unhide,
+            hide_current,
+            restore,
+            restore_with,
             ..
         } = mode
-        {
-            // Get currently focused window address and class
-            eprintln!("[hyprctl-hide][swap] Fetching currently focused window address...");
-            let curr_json = std::process::Command::new("hyprctl")
-                .arg("activewindow")
-                .arg("-j")
-                .output()
-                .map(|o| o.stdout)
-                .unwrap_or_default();
-            let curr_val =
-                serde_json::from_slice::<serde_json::Value>(&curr_json).unwrap_or_default();
-            let curr_addr = curr_val
-                .get("address")
-                .and_then(|a| a.as_str())
-                .unwrap_or("")
-                .to_string();
-            let curr_class = curr_val
-                .get("class")
-                .and_then(|c| c.as_str())
-                .unwrap_or("")
-                .to_string();
-            eprintln!(
-                "[hyprctl-hide][swap] Current window address: '{}'",
-                curr_addr
-            );
-            eprintln!(
-                "[hyprctl-hide][swap] Current window class: '{}'",
-                curr_class
-            );
-            eprintln!(
-                "[hyprctl-hide][swap] Target window address: '{}'",
-                target_addr
-            );
-
-            // If ignore_class is set and current window matches, hide previously focused window instead
-            let mut hide_addr = curr_addr.clone();
-            if let Some(ref ignore) = self.ignore_class {
-                if curr_class == *ignore {
-                    eprintln!(
-                        "[hyprctl-hide][swap] Current window matches ignore_class ('{}'), searching for previously focused window to hide...",
-                        ignore
-                    );
-                    // Get all clients and sort by focusHistoryID descending, skip ignored class and current
-                    let clients_json = std::process::Command::new("hyprctl")
-                        .arg("clients")
-                        .arg("-j")
-                        .output()
-                        .map(|o| o.stdout)
-                        .unwrap_or_default();
-                    let clients: Vec<Client> =
-                        serde_json::from_slice(&clients_json).unwrap_or_default();
-                    if let Some(prev) = clients
-                        .iter()
-                        .filter(|c| {
-                            c.class != *ignore && c.address != curr_addr && c.focusHistoryID > 0
-                        })
-                        .min_by_key(|c| c.focusHistoryID)
-                    {
-                        hide_addr = prev.address.clone();
-                        eprintln!(
-                            "[hyprctl-hide][swap] Hiding previously focused window: '{}', class: '{}', focusHistoryID: {}",
-                            hide_addr, prev.class, prev.focusHistoryID
-                        );
-                    } else {
-                        eprintln!(
-                            "[hyprctl-hide][swap] No suitable previously focused window found, will not hide any window."
-                        );
-                        hide_addr.clear();
-                    }
-                }
-            }
+        else {
+            return true;
+        };
+        // The actions run and exit immediately, without starting the TUI
+        let result = if let Some(target_addr) = swap {
+            self.swap(target_addr)
+        } else if let Some(address) = unhide {
+            Self::unhide(address)
+        } else if *hide_current {
+            self.hide_current()
+        } else if *restore {
+            compositor::from_env().and_then(|wm| self.restore(&wm.stash_workspace(&self.stash)))
+        } else if let Some(address) = restore_with {
+            self.restore_with(address)
+        } else {
+            return true;
+        };
+        if let Err(e) = result {
+            eprintln!("[hyprctl-hide] {e:#}");
+        }
+        false
+    }
+}
 
-            // Move window to hidden if we have an address
-            if !hide_addr.is_empty() {
-                let hide_cmd = format!(
-                    "hyprctl dispatch movetoworkspacesilent special:hidden,address:{}",
-                    hide_addr
-                );
-                eprintln!("[hyprctl-hide][swap] Executing: {}", hide_cmd);
-                let _ = std::process::Command::new("hyprctl")
-                    .args(&[
-                        "dispatch",
-                        "movetoworkspacesilent",
-                        &format!("special:hidden,address:{}", hide_addr),
-                    ])
-                    .status();
+impl HyprctlHide {
+    /// The focused window, or the previously focused one if it has the ignored class
+    fn window_to_hide(&self, wm: &dyn Compositor) -> Result<Option<Client>> {
+        let Some(curr) = wm.active_window()? else {
+            return Ok(None);
+        };
+        eprintln!(
+            "[hyprctl-hide] Current window: '{}' ({})",
+            curr.address, curr.class
+        );
+        if self.ignore_class.as_ref() != Some(&curr.class) {
+            return Ok(Some(curr));
+        }
+        // Stashed windows, the swap target among them, are not candidates
+        let is_stash = wm.stash_predicate(&self.stash);
+        let clients = wm
+            .clients()?
+            .into_iter()
+            .filter(|c| !is_stash(&c.workspace))
+            .collect();
+        let prev = pick_window_to_hide(curr, clients, self.ignore_class.as_deref());
+        eprintln!(
+            "[hyprctl-hide] Current window matches ignore_class ({:?}), hiding previously focused window: {:?}",
+            self.ignore_class,
+            prev.as_ref().map(|c| &c.address)
+        );
+        Ok(prev)
+    }
+
+    /// Hides the focused window (or the previously focused one if it has the ignored class)
+    /// into the stash of `target_addr`, and brings `target_addr` to the current workspace.
+    fn swap(&self, target_addr: &str) -> Result<()> {
+        let wm = compositor::from_env()?;
+        eprintln!("[hyprctl-hide][swap] Target window: '{target_addr}'");
+        let target_stash = stash_of(&*wm, target_addr, &self.stash)?
+            .map_or_else(|| wm.stash_workspace(&self.stash), |ws| ws.name);
+        let hidden = self.window_to_hide(&*wm)?;
+        let workspace = current_workspace(&*wm)?;
+        eprintln!("[hyprctl-hide][swap] Current workspace: {workspace}");
+
+        let mut dispatches = Vec::new();
+        if let Some(ref hidden) = hidden {
+            dispatches.push(Dispatch::MoveToWorkspaceSilent {
+                workspace: target_stash.clone(),
+                address: hidden.address.clone(),
+            });
+        }
+        dispatches.extend(bring(&workspace, &[target_addr]));
+        if let Err(e) = wm.batch(&dispatches) {
+            // Either move may have gone through alone
+            if let Err(rollback) = roll_back(
+                &*wm,
+                hidden.as_ref(),
+                target_addr,
+                &target_stash,
+                &workspace,
+            ) {
+                eprintln!("[hyprctl-hide][swap] Rollback failed: {rollback:#}");
             }
+            return Err(e.context("Failed to swap the windows"));
+        }
+        eprintln!("[hyprctl-hide][swap] Swap complete, exiting.");
+        Ok(())
+    }
+
+    /// Hides the focused window (or the previously focused one if it has the ignored class)
+    /// into `--stash`
+    fn hide_current(&self) -> Result<()> {
+        let wm = compositor::from_env()?;
+        let window = self.window_to_hide(&*wm)?.context("No window to hide")?;
+        wm.dispatch(&Dispatch::MoveToWorkspaceSilent {
+            workspace: wm.stash_workspace(&self.stash),
+            address: window.address,
+        })
+    }
+
+    /// Brings the window at `address` to the current workspace
+    fn unhide(address: &str) -> Result<()> {
+        let wm = compositor::from_env()?;
+        let workspace = current_workspace(&*wm)?;
+        wm.batch(&bring(&workspace, &[address]))
+    }
+
+    /// Brings every window of the stash `workspace` to the current workspace
+    fn restore(&self, workspace: &str) -> Result<()> {
+        let wm = compositor::from_env()?;
+        let mut windows: Vec<Client> = wm
+            .clients()?
+            .into_iter()
+            .filter(|c| {
+                c.workspace.name == workspace && self.ignore_class.as_ref() != Some(&c.class)
+            })
+            .collect();
+        if windows.is_empty() {
+            bail!("The stash {workspace} is empty");
+        }
+        // The most recently used window gets the focus
+        windows.sort_by_key(|c| c.focus_history_id);
+        let addresses: Vec<&str> = windows.iter().map(|c| c.address.as_str()).collect();
+        wm.batch(&bring(&current_workspace(&*wm)?, &addresses))
+    }
 
-            // Move target window to current workspace
-            let ws_json = std::process::Command::new("hyprctl")
-                .arg("activeworkspace")
-                .arg("-j")
-                .output()
-                .map(|o| o.stdout)
-                .unwrap_or_default();
-            let ws_id = serde_json::from_slice::<serde_json::Value>(&ws_json)
-                .and_then(|v| Ok(v.get("id").and_then(|i| i.as_i64()).unwrap_or(1)))
-                .unwrap_or(1);
-            eprintln!("[hyprctl-hide][swap] Current workspace id: {}", ws_id);
-
-            let bring_cmd = format!(
-                "hyprctl dispatch movetoworkspacesilent {},address:{}",
-                ws_id, target_addr
-            );
-            eprintln!("[hyprctl-hide][swap] Executing: {}", bring_cmd);
-            let _ = std::process::Command::new("hyprctl")
-                .args(&[
-                    "dispatch",
-                    "movetoworkspacesilent",
-                    &format!("{},address:{}", ws_id, target_addr),
-                ])
-                .status();
-
-            let focus_cmd = format!("hyprctl dispatch focuswindow address:{}", target_addr);
-            eprintln!("[hyprctl-hide][swap] Executing: {}", focus_cmd);
-            let _ = std::process::Command::new("hyprctl")
-                .args(&[
-                    "dispatch",
-                    "focuswindow",
-                    &format!("address:{}", target_addr),
-                ])
-                .status();
-            // Exit immediately, do not start TUI
-            eprintln!("[hyprctl-hide][swap] Swap complete, exiting.");
-            return false;
+    /// Restores the whole stash holding the window at `address`
+    fn restore_with(&self, address: &str) -> Result<()> {
+        let workspace = stash_of(&*compositor::from_env()?, address, &self.stash)?
+            .with_context(|| format!("Window {address} is not in a stash"))?;
+        self.restore(&workspace.name)
+    }
+}
+
+/// The workspace windows are brought to, as a dispatcher argument
+fn current_workspace(wm: &dyn Compositor) -> Result<String> {
+    let workspace = wm.active_workspace()?;
+    // Hyprland replies `{}` when no workspace is active, moving windows to a default one
+    // would send them anywhere
+    if workspace.name.is_empty() {
+        bail!("Could not determine the active workspace");
+    }
+    Ok(workspace.selector())
+}
+
+/// The window to hide when the focused one, `active`, has the ignored class:
+/// the most recently focused window among `clients` without that class
+fn pick_window_to_hide(
+    active: Client,
+    clients: Vec<Client>,
+    ignore_class: Option<&str>,
+) -> Option<Client> {
+    let Some(ignore) = ignore_class.filter(|ignore| active.class == *ignore) else {
+        return Some(active);
+    };
+    clients
+        .into_iter()
+        .filter(|c| c.class != ignore && c.address != active.address && c.focus_history_id > 0)
+        .min_by_key(|c| c.focus_history_id)
+}
+
+/// Undoes the half of a swap that went through: the hidden window goes back to where it was
+/// if the target was not brought, the target back to its stash if the other one was not hidden
+fn roll_back(
+    wm: &dyn Compositor,
+    hidden: Option<&Client>,
+    target_addr: &str,
+    target_stash: &str,
+    workspace: &str,
+) -> Result<()> {
+    let clients = wm.clients()?;
+    let workspace_of = |address: &str| {
+        clients
+            .iter()
+            .find(|c| c.address == address)
+            .map(|c| c.workspace.clone())
+    };
+    let target_brought = workspace_of(target_addr).is_some_and(|ws| ws.selector() == workspace);
+    let hidden_moved = hidden.filter(|h| {
+        workspace_of(&h.address).is_some_and(|ws| ws.name == target_stash && ws != h.workspace)
+    });
+    let dispatches = match (hidden_moved, target_brought) {
+        (Some(hidden), false) => vec![
+            Dispatch::MoveToWorkspaceSilent {
+                workspace: hidden.workspace.selector(),
+                address: hidden.address.clone(),
+            },
+            Dispatch::FocusWindow {
+                address: hidden.address.clone(),
+            },
+        ],
+        (None, true) if hidden.is_some() => vec![Dispatch::MoveToWorkspaceSilent {
+            workspace: target_stash.to_string(),
+            address: target_addr.to_string(),
+        }],
+        _ => return Ok(()),
+    };
+    eprintln!("[hyprctl-hide][swap] Rolling back: {dispatches:?}");
+    wm.batch(&dispatches)
+}
+
+/// The stash holding the window at `address`, if any, `stash` being the one in use
+fn stash_of(wm: &dyn Compositor, address: &str, stash: &str) -> Result<Option<WorkspaceRef>> {
+    let is_stash = wm.stash_predicate(stash);
+    Ok(wm
+        .clients()?
+        .into_iter()
+        .find(|c| c.address == address)
+        .map(|c| c.workspace)
+        .filter(|ws| is_stash(ws)))
+}
+
+/// Moves the windows at `addresses` to `workspace` and focuses the first one
+fn bring(workspace: &str, addresses: &[&str]) -> Vec<Dispatch> {
+    let mut dispatches: Vec<Dispatch> = addresses
+        .iter()
+        .map(|address| Dispatch::MoveToWorkspaceSilent {
+            workspace: workspace.to_string(),
+            address: (*address).to_string(),
+        })
+        .collect();
+    if let Some(first) = addresses.first() {
+        dispatches.push(Dispatch::FocusWindow {
+            address: (*first).to_string(),
+        });
+    }
+    dispatches
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use anyhow::bail;
+
+    use super::*;
+    use crate::hyprland::{Monitor, Workspace, events::Event};
+
+    /// A compositor with fixed windows and events, recording the dispatches it gets
+    #[derive(Default)]
+    struct FakeCompositor {
+        clients: Vec<Client>,
+        events: Vec<Event>,
+        batches: RefCell<Vec<Vec<Dispatch>>>,
+    }
+
+    impl Compositor for FakeCompositor {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+        fn clients(&self) -> Result<Vec<Client>> {
+            Ok(self.clients.clone())
+        }
+        fn active_workspace(&self) -> Result<Workspace> {
+            bail!("not needed")
+        }
+        fn monitors(&self) -> Result<Vec<Monitor>> {
+            Ok(Vec::new())
+        }
+        fn batch(&self, dispatches: &[Dispatch]) -> Result<()> {
+            self.batches.borrow_mut().push(dispatches.to_vec());
+            Ok(())
+        }
+        fn events(&self) -> Result<Box<dyn Iterator<Item = Event> + Send>> {
+            Ok(Box::new(self.events.clone().into_iter()))
+        }
+    }
+
+    fn workspace(id: i64, name: &str) -> WorkspaceRef {
+        WorkspaceRef {
+            id,
+            name: name.to_string(),
+        }
+    }
+
+    fn client(address: &str, class: &str, focus_history_id: i32, ws: WorkspaceRef) -> Client {
+        Client {
+            address: address.to_string(),
+            class: class.to_string(),
+            focus_history_id,
+            workspace: ws,
+            ..Client::default()
         }
-        true
+    }
+
+    fn picked(active: Client, clients: Vec<Client>, ignore_class: Option<&str>) -> Option<String> {
+        pick_window_to_hide(active, clients, ignore_class).map(|c| c.address)
+    }
+
+    #[test]
+    fn ignored_active_window() {
+        let ws = workspace(1, "1");
+        let active = client("0x1", "kitty", 0, ws.clone());
+        let clients = vec![
+            active.clone(),
+            // Another terminal of the ignored class, more recent than the browser
+            client("0x2", "kitty", 1, ws.clone()),
+            client("0x3", "firefox", 2, ws.clone()),
+            client("0x4", "mpv", 3, ws.clone()),
+            client("0x5", "keepassxc", -1, workspace(-98, "special:hidden")),
+        ];
+        assert_eq!(
+            picked(active.clone(), clients.clone(), Some("kitty")),
+            Some(String::from("0x3"))
+        );
+        // Windows without focus history are never picked
+        let clients = vec![active.clone(), clients[4].clone()];
+        assert_eq!(picked(active, clients, Some("kitty")), None);
+    }
+
+    #[test]
+    fn active_window_without_ignore_class() {
+        let ws = workspace(1, "1");
+        let active = client("0x1", "kitty", 0, ws.clone());
+        let clients = vec![active.clone(), client("0x3", "firefox", 1, ws)];
+        assert_eq!(
+            picked(active.clone(), clients.clone(), None),
+            Some(String::from("0x1"))
+        );
+        // The active window does not have the ignored class
+        assert_eq!(
+            picked(active, clients, Some("Alacritty")),
+            Some(String::from("0x1"))
+        );
+    }
+
+    #[test]
+    fn no_window_to_hide() {
+        let active = client("0x1", "kitty", 0, workspace(1, "1"));
+        assert_eq!(picked(active, Vec::new(), Some("kitty")), None);
+        // Only the picker is open, `--hide-current` and the swap have nothing to hide
+        let wm = FakeCompositor {
+            clients: vec![client("0x1", "kitty", 0, workspace(1, "1"))],
+            ..FakeCompositor::default()
+        };
+        let hide = HyprctlHide {
+            ignore_class: Some(String::from("kitty")),
+            stash: DEFAULT_STASH.to_string(),
+            all: false,
+        };
+        assert!(hide.window_to_hide(&wm).unwrap().is_none());
+    }
+
+    #[test]
+    fn roll_back_failed_swap() {
+        let stash = stash_workspace(DEFAULT_STASH);
+        let hidden = client("0x3", "firefox", 1, workspace(1, "1"));
+        let target = "0x9";
+        let after = |hidden_ws: WorkspaceRef, target_ws: WorkspaceRef| FakeCompositor {
+            clients: vec![
+                client(&hidden.address, "firefox", 1, hidden_ws),
+                client(target, "mpv", -1, target_ws),
+            ],
+            ..FakeCompositor::default()
+        };
+        let stashed = workspace(-98, &stash);
+        let current = workspace(1, "1");
+
+        // Only the hide went through: the window comes back
+        let wm = after(stashed.clone(), stashed.clone());
+        roll_back(&wm, Some(&hidden), target, &stash, "1").unwrap();
+        assert_eq!(
+            wm.batches.into_inner(),
+            [vec![
+                Dispatch::MoveToWorkspaceSilent {
+                    workspace: String::from("1"),
+                    address: String::from("0x3"),
+                },
+                Dispatch::FocusWindow {
+                    address: String::from("0x3"),
+                },
+            ]]
+        );
+
+        // Only the target was brought: it goes back to its stash
+        let wm = after(current.clone(), current.clone());
+        roll_back(&wm, Some(&hidden), target, &stash, "1").unwrap();
+        assert_eq!(
+            wm.batches.into_inner(),
+            [vec![Dispatch::MoveToWorkspaceSilent {
+                workspace: stash.clone(),
+                address: target.to_string(),
+            }]]
+        );
+
+        // Nothing or everything went through: nothing to undo
+        for wm in [
+            after(current.clone(), stashed.clone()),
+            after(stashed.clone(), current.clone()),
+        ] {
+            roll_back(&wm, Some(&hidden), target, &stash, "1").unwrap();
+            assert!(wm.batches.into_inner().is_empty());
+        }
+        // Nothing was hidden, the target stays wherever it got
+        let wm = after(current.clone(), current);
+        roll_back(&wm, None, target, &stash, "1").unwrap();
+        assert!(wm.batches.into_inner().is_empty());
+    }
+
+    #[test]
+    fn closed_windows_stay_marked() {
+        let stash = workspace(-98, "special:hidden");
+        let wm = FakeCompositor {
+            events: vec![
+                Event::CloseWindow {
+                    address: String::from("0x1"),
+                },
+                Event::MoveWindow {
+                    address: String::from("0x2"),
+                    workspace: workspace(1, "1"),
+                },
+            ],
+            ..FakeCompositor::default()
+        };
+        let mut firefox = client("0x1", "firefox", -1, stash.clone());
+        firefox.title = String::from("Mozilla Firefox");
+        firefox.mapped = true;
+        let mut mpv = client("0x2", "mpv", -1, stash);
+        mpv.title = String::from("video.mkv");
+        mpv.mapped = true;
+        let (tx, rx) = skim::prelude::unbounded();
+        live::stream(
+            &wm,
+            vec![firefox, mpv],
+            tx,
+            |_| true,
+            |client| {
+                Arc::new(ClassWindow {
+                    client,
+                    stash: Some(stash_workspace(DEFAULT_STASH)),
+                    is_stash: Arc::new(|_| true),
+                })
+            },
+        );
+        // The event thread drops the sender once it went through the events
+        let items: Vec<_> = rx.iter().collect();
+        let texts: Vec<_> = items.iter().map(|item| item.text().into_owned()).collect();
+        assert_eq!(
+            texts,
+            [
+                "Mozilla Firefox [firefox] (closed)",
+                "video.mkv [mpv] (unhidden)"
+            ]
+        );
     }
 }
@@ -0,0 +1,359 @@
+use std::{borrow::Cow, fs, path::PathBuf, sync::Arc};
+
+use anyhow::{Context as _, Result, bail};
+use skim::{ItemPreview, PreviewContext, SkimItem, SkimOutput};
+
+use crate::{
+    SkimRun,
+    hyprland::{Dispatch, Hyprland, Monitor},
+    xdg,
+};
+
+static PROFILES_DIR: &str = "monitors";
+static PROFILE_EXT: &str = "conf";
+/// Prefix of the output of profile items, telling them apart from monitor names in binds
+static PROFILE_PREFIX: &str = "profile:";
+
+/// A saved monitor layout, the `monitor` keywords of a file in the profiles dir
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    /// File name without the extension
+    pub name: String,
+    /// Values of the `monitor` keyword, like `DP-1,2560x1440@144,0x0,1`
+    pub monitors: Vec<String>,
+}
+
+impl Profile {
+    /// Reads the profile at `path`.
+    /// Each line is a monitor rule, `monitor =` prefixes are allowed so lines can be copied
+    /// from `hyprland.conf`, empty lines and `#` comments are skipped.
+    ///
+    /// # Errors
+    /// Returns an error if the file can not be read.
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let monitors = content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| {
+                l.strip_prefix("monitor")
+                    .and_then(|rest| rest.trim_start().strip_prefix('='))
+                    .unwrap_or(l)
+                    .trim()
+                    .to_string()
+            })
+            .collect();
+        Ok(Self {
+            name: path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            monitors,
+        })
+    }
+
+    fn path(name: &str) -> PathBuf {
+        profiles_dir().join(format!("{name}.{PROFILE_EXT}"))
+    }
+
+    /// Sets all the monitor rules of the profile in a single request
+    fn apply(&self, hypr: &Hyprland) -> Result<()> {
+        if self.monitors.is_empty() {
+            bail!("The profile {} has no monitor rules", self.name);
+        }
+        hypr.keywords("monitor", &self.monitors)
+            .with_context(|| format!("Failed to apply the profile {}", self.name))
+    }
+}
+
+/// Directory holding the layout profiles, `$XDG_CONFIG_HOME/skim-run/monitors`
+#[must_use]
+pub fn profiles_dir() -> PathBuf {
+    xdg::config_dir().join(PROFILES_DIR)
+}
+
+/// Every profile of the profiles dir, sorted by name
+#[must_use]
+pub fn profiles() -> Vec<Profile> {
+    let mut files: Vec<PathBuf> = fs::read_dir(profiles_dir())
+        .map(|dir| {
+            dir.filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == PROFILE_EXT))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+        .iter()
+        .filter_map(|path| {
+            Profile::load(path)
+                .inspect_err(|e| eprintln!("[hyprctl-monitors] {e:#}"))
+                .ok()
+        })
+        .collect()
+}
+
+/// `SkimItem` wrapper for a Hyprland monitor
+struct MonitorItem {
+    monitor: Monitor,
+}
+
+impl SkimItem for MonitorItem {
+    fn text(&self) -> Cow<'_, str> {
+        let m = &self.monitor;
+        let states: Vec<&str> = [
+            (m.focused, "focused"),
+            (!m.dpms_status, "off"),
+            (m.disabled, "disabled"),
+        ]
+        .into_iter()
+        .filter_map(|(on, state)| on.then_some(state))
+        .collect();
+        Cow::Owned(format!(
+            "{}: {}x{}@{:.2}Hz scale {} at {}x{} - workspace {}{}",
+            m.name,
+            m.width,
+            m.height,
+            m.refresh_rate,
+            m.scale,
+            m.x,
+            m.y,
+            m.active_workspace.name,
+            if states.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", states.join(", "))
+            }
+        ))
+    }
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.monitor.name)
+    }
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        let m = &self.monitor;
+        let (left, top, right, bottom) = m.reserved;
+        ItemPreview::Text(format!(
+            "Name: {} (id {})\nDescription: {}\nMake: {}\nModel: {}\nSerial: {}\n\
+             Mode: {}x{}@{:.2}Hz\nScale: {}\nPosition: {}x{}\nTransform: {}\n\
+             Active workspace: {}\nSpecial workspace: {}\nReserved: {left} {top} {right} {bottom}\n\
+             DPMS: {}\nVRR: {}\nFormat: {}\nMirror of: {}\n\nAvailable modes:\n{}",
+            m.name,
+            m.id,
+            m.description,
+            m.make,
+            m.model,
+            m.serial,
+            m.width,
+            m.height,
+            m.refresh_rate,
+            m.scale,
+            m.x,
+            m.y,
+            m.transform,
+            m.active_workspace.name,
+            m.special_workspace.name,
+            if m.dpms_status { "on" } else { "off" },
+            m.vrr,
+            m.current_format,
+            m.mirror_of,
+            m.available_modes.join("\n")
+        ))
+    }
+}
+
+/// `SkimItem` wrapper for a saved layout profile
+struct ProfileItem {
+    profile: Profile,
+}
+
+impl SkimItem for ProfileItem {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Owned(format!(
+            "profile {}: {}",
+            self.profile.name,
+            self.profile.monitors.join("; ")
+        ))
+    }
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Owned(format!("{PROFILE_PREFIX}{}", self.profile.name))
+    }
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        ItemPreview::Text(format!(
+            "Profile: {}\nFile: {}\n\n{}",
+            self.profile.name,
+            Profile::path(&self.profile.name).display(),
+            self.profile
+                .monitors
+                .iter()
+                .map(|m| format!("monitor = {m}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))
+    }
+}
+
+/// Mode listing the monitors, then the saved layout profiles.
+/// Focuses the selected monitor or applies the selected profile (enter),
+/// moves the current workspace to the monitor (alt-enter) or turns it off and on (alt-o).
+pub struct HyprctlMonitors;
+
+impl SkimRun for HyprctlMonitors {
+    fn init(&self, mode: &crate::Mode) -> bool {
+        let crate::Mode::HyprctlMonitors {
+            move_workspace,
+            toggle_dpms,
+            profile,
+        } = mode
+        else {
+            return true;
+        };
+        let dispatch = if let Some(monitor) = move_workspace {
+            monitor_name(monitor).map(|monitor| Dispatch::MoveCurrentWorkspaceToMonitor {
+                monitor: monitor.to_string(),
+            })
+        } else if let Some(monitor) = toggle_dpms {
+            monitor_name(monitor).map(|monitor| Dispatch::ToggleDpms {
+                monitor: monitor.to_string(),
+            })
+        } else if let Some(name) = profile {
+            let name = name.strip_prefix(PROFILE_PREFIX).unwrap_or(name);
+            if let Err(e) = Hyprland::from_env().and_then(|hypr| apply_profile(&hypr, name)) {
+                eprintln!("[hyprctl-monitors] {e:#}");
+            }
+            return false;
+        } else {
+            return true;
+        };
+        if let Err(e) = dispatch.and_then(|dispatch| Hyprland::from_env()?.dispatch(&dispatch)) {
+            eprintln!("[hyprctl-monitors] {e:#}");
+        }
+        false
+    }
+
+    fn get(&self) -> Vec<Arc<dyn SkimItem>> {
+        let hypr = Hyprland::from_env().expect("Failed to connect to hyprland");
+        let mut monitors = hypr.monitors().expect("Failed to get hyprland monitors");
+        // Left to right, then top to bottom
+        monitors.sort_by_key(|m| (m.x, m.y));
+        monitors
+            .into_iter()
+            .map(|monitor| Arc::new(MonitorItem { monitor }) as Arc<dyn SkimItem>)
+            .chain(
+                profiles()
+                    .into_iter()
+                    .map(|profile| Arc::new(ProfileItem { profile }) as Arc<dyn SkimItem>),
+            )
+            .collect()
+    }
+
+    fn set_options(&self, opts: &mut skim::SkimOptions) {
+        opts.preview = Some(String::new());
+        opts.preview_window = String::from("up:40%");
+        opts.header = Some(format!(
+            "Enter: focus monitor or apply profile. Alt-Enter: move current workspace there. Alt-o: toggle DPMS. Profiles are read from {}",
+            profiles_dir().display()
+        ));
+        opts.bind.extend(vec![
+            "alt-enter:accept(hyprctl-monitors --move-workspace {})".to_string(),
+            // Stays open, the state shows on the next run
+            format!(
+                "alt-o:execute-silent({} hyprctl-monitors --toggle-dpms {{}})",
+                crate::current_exe()
+            ),
+        ]);
+    }
+
+    fn run(&self, output: &SkimOutput) -> Result<()> {
+        let item = output
+            .selected_items
+            .first()
+            .context("No monitor selected")?;
+        let hypr = Hyprland::from_env()?;
+        if let Some(profile) = item.as_any().downcast_ref::<ProfileItem>() {
+            return profile.profile.apply(&hypr);
+        }
+        hypr.dispatch(&Dispatch::FocusMonitor {
+            monitor: item.output().to_string(),
+        })
+        .context("Failed to focus monitor")
+    }
+}
+
+/// The monitor named by a bind's `{}`, which is a profile on profile rows
+fn monitor_name(arg: &str) -> Result<&str> {
+    match arg.strip_prefix(PROFILE_PREFIX) {
+        Some(profile) => bail!("{profile} is a profile, pick a monitor"),
+        None => Ok(arg),
+    }
+}
+
+fn apply_profile(hypr: &Hyprland, name: &str) -> Result<()> {
+    let path = Profile::path(name);
+    if !path.is_file() {
+        bail!("No profile {name} in {}", profiles_dir().display());
+    }
+    Profile::load(&path)?.apply(hypr)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn load(content: &str) -> Profile {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("desk.conf");
+        fs::write(&path, content).unwrap();
+        Profile::load(&path).unwrap()
+    }
+
+    #[test]
+    fn profile_rules() {
+        let profile = load(
+            "# docked\n\
+             monitor = DP-1,2560x1440@144,0x0,1\n\
+             \n\
+             monitor=eDP-1,disable\n\
+             \t  HDMI-A-1,preferred,auto,1  \n\
+             monitors_are_not_a_prefix\n\
+             # monitor = DP-2,disable\n",
+        );
+        assert_eq!(profile.name, "desk");
+        assert_eq!(
+            profile.monitors,
+            [
+                "DP-1,2560x1440@144,0x0,1",
+                "eDP-1,disable",
+                "HDMI-A-1,preferred,auto,1",
+                "monitors_are_not_a_prefix",
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_profile() {
+        let profile = load("");
+        assert!(profile.monitors.is_empty());
+        assert!(load("# nothing yet\n\n").monitors.is_empty());
+        assert!(Profile::load(Path::new("/nonexistent/desk.conf")).is_err());
+    }
+
+    #[test]
+    fn profile_rows_are_not_monitors() {
+        let profile = ProfileItem {
+            profile: Profile {
+                name: String::from("desk"),
+                monitors: Vec::new(),
+            },
+        };
+        assert_eq!(profile.output(), "profile:desk");
+        assert_eq!(monitor_name("DP-1").unwrap(), "DP-1");
+        assert_eq!(
+            monitor_name(&profile.output()).unwrap_err().to_string(),
+            "desk is a profile, pick a monitor"
+        );
+    }
+}
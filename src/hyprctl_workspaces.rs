@@ -0,0 +1,233 @@
+use std::{borrow::Cow, sync::Arc};
+
+use anyhow::{Context as _, Result, bail};
+use skim::{ItemPreview, PreviewContext, SkimItem, SkimOutput};
+
+use crate::{
+    SkimRun,
+    compositor::Compositor as _,
+    hyprland::{Client, Dispatch, Hyprland, Workspace},
+};
+
+/// `SkimItem` wrapper for a Hyprland workspace and its windows
+struct WorkspaceItem {
+    workspace: Workspace,
+    windows: Vec<Client>,
+}
+
+impl SkimItem for WorkspaceItem {
+    fn text(&self) -> Cow<'_, str> {
+        let titles: Vec<&str> = self.windows.iter().map(|c| c.title.as_str()).collect();
+        Cow::Owned(format!(
+            "{} on {} - {} window{}{}{}",
+            self.workspace.name,
+            self.workspace.monitor,
+            self.workspace.windows,
+            if self.workspace.windows == 1 { "" } else { "s" },
+            if titles.is_empty() { "" } else { ": " },
+            titles.join(", ")
+        ))
+    }
+    fn output(&self) -> Cow<'_, str> {
+        // Names may contain spaces, the id survives the re-exec arguments
+        Cow::Owned(self.workspace.id.to_string())
+    }
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        let windows: Vec<String> = self
+            .windows
+            .iter()
+            .map(|c| format!("{} [{}]", c.title, c.class))
+            .collect();
+        ItemPreview::Text(format!(
+            "Workspace: {} (id {})\nMonitor: {}\nWindows: {}\n\n{}",
+            self.workspace.name,
+            self.workspace.id,
+            self.workspace.monitor,
+            self.workspace.windows,
+            windows.join("\n")
+        ))
+    }
+}
+
+/// Mode listing the workspaces, special ones included.
+/// Switches to the selected workspace (enter), moves the window focused before the picker there (alt-enter),
+/// renames it to the query (ctrl-r) or creates a workspace named after the query (ctrl-n).
+/// With `window`, picks the workspace to send that window to instead.
+pub struct HyprctlWorkspaces {
+    pub window: Option<String>,
+}
+
+impl SkimRun for HyprctlWorkspaces {
+    fn init(&self, mode: &crate::Mode) -> bool {
+        let crate::Mode::HyprctlWorkspaces {
+            move_window,
+            rename,
+            create,
+            name,
+            ..
+        } = mode
+        else {
+            return true;
+        };
+        let name = name.join(" ");
+        let result = if let Some(id) = move_window {
+            move_window_to(*id, self.window.as_deref())
+        } else if let Some(id) = rename {
+            rename_workspace(*id, &name)
+        } else if *create {
+            create_workspace(&name, self.window.as_deref())
+        } else {
+            return true;
+        };
+        if let Err(e) = result {
+            eprintln!("[hyprctl-workspaces] {e:#}");
+        }
+        false
+    }
+
+    fn get(&self) -> Vec<Arc<dyn SkimItem>> {
+        items().unwrap_or_else(|e| {
+            eprintln!("[hyprctl-workspaces] {e:#}");
+            Vec::new()
+        })
+    }
+
+    fn set_options(&self, opts: &mut skim::SkimOptions) {
+        opts.preview = Some(String::new());
+        opts.preview_window = String::from("up:40%");
+        if let Some(ref window) = self.window {
+            opts.header = Some(
+                "Enter: move the window here. Ctrl-n: move it to a new workspace named query."
+                    .to_string(),
+            );
+            opts.bind.extend(vec![
+                format!("enter:accept(hyprctl-workspaces --window {window} --move-window {{}})"),
+                format!("ctrl-n:accept(hyprctl-workspaces --window {window} --create {{q}})"),
+            ]);
+            return;
+        }
+        opts.header = Some(
+            "Enter: switch. Alt-Enter: move the previous window here. Ctrl-r: rename to query. Ctrl-n: new workspace named query."
+                .to_string(),
+        );
+        opts.bind.extend(vec![
+            "alt-enter:accept(hyprctl-workspaces --move-window {})".to_string(),
+            "ctrl-r:accept(hyprctl-workspaces --rename {} {q})".to_string(),
+            "ctrl-n:accept(hyprctl-workspaces --create {q})".to_string(),
+        ]);
+    }
+
+    fn run(&self, output: &SkimOutput) -> Result<()> {
+        let result = output
+            .selected_items
+            .first()
+            .context("No workspace selected")?;
+        let id = result.output().parse().context("Invalid workspace id")?;
+        let hypr = Hyprland::from_env()?;
+        let workspace = find(&hypr, id)?;
+        let dispatch = if workspace.is_special() {
+            Dispatch::ToggleSpecialWorkspace {
+                name: special_name(&workspace),
+            }
+        } else {
+            Dispatch::Workspace {
+                workspace: workspace.selector(),
+            }
+        };
+        hypr.dispatch(&dispatch)
+            .context("Failed to switch workspace")
+    }
+}
+
+fn items() -> Result<Vec<Arc<dyn SkimItem>>> {
+    let hypr = Hyprland::from_env()?;
+    let mut workspaces = hypr
+        .workspaces()
+        .context("Failed to get hyprland workspaces")?;
+    let clients = hypr.clients().context("Failed to get hyprland clients")?;
+    // Regular workspaces first, by id
+    workspaces.sort_by_key(|w| (w.is_special(), w.id));
+    Ok(workspaces
+        .into_iter()
+        .map(|workspace| {
+            let windows = clients
+                .iter()
+                .filter(|c| c.workspace.id == workspace.id)
+                .cloned()
+                .collect();
+            Arc::new(WorkspaceItem { workspace, windows }) as Arc<dyn SkimItem>
+        })
+        .collect())
+}
+
+fn find(hypr: &Hyprland, id: i64) -> Result<Workspace> {
+    hypr.workspaces()?
+        .into_iter()
+        .find(|w| w.id == id)
+        .with_context(|| format!("Workspace {id} does not exist anymore"))
+}
+
+/// Name of a special workspace as `togglespecialworkspace` expects it
+fn special_name(workspace: &Workspace) -> String {
+    workspace
+        .name
+        .strip_prefix("special:")
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Moves `window`, or the window focused before the picker, to the workspace.
+/// The latter is followed there, as `movetoworkspace` would do.
+fn move_window_to(id: i64, window: Option<&str>) -> Result<()> {
+    let hypr = Hyprland::from_env()?;
+    let workspace = find(&hypr, id)?.selector();
+    let dispatches = if let Some(address) = window {
+        vec![Dispatch::MoveToWorkspaceSilent {
+            workspace,
+            address: address.to_string(),
+        }]
+    } else {
+        let address = hypr
+            .previous_window()?
+            .context("No window was focused before the picker")?
+            .address;
+        vec![
+            Dispatch::MoveToWorkspaceSilent {
+                workspace,
+                address: address.clone(),
+            },
+            Dispatch::FocusWindow { address },
+        ]
+    };
+    hypr.batch(&dispatches).context("Failed to move the window")
+}
+
+fn rename_workspace(id: i64, name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!("Type the new workspace name in the query");
+    }
+    Hyprland::from_env()?
+        .dispatch(&Dispatch::RenameWorkspace {
+            id,
+            name: name.to_string(),
+        })
+        .context("Failed to rename workspace")
+}
+
+/// Creates the workspace `name` and switches to it, or sends `window` there
+fn create_workspace(name: &str, window: Option<&str>) -> Result<()> {
+    if name.is_empty() {
+        bail!("Type the new workspace name in the query");
+    }
+    let workspace = format!("name:{name}");
+    let dispatch = match window {
+        Some(address) => Dispatch::MoveToWorkspaceSilent {
+            workspace,
+            address: address.to_string(),
+        },
+        None => Dispatch::Workspace { workspace },
+    };
+    Hyprland::from_env()?
+        .dispatch(&dispatch)
+        .context("Failed to create workspace")
+}
@@ -0,0 +1,200 @@
+use std::{
+    io::{BufRead as _, BufReader},
+    os::unix::net::UnixStream,
+};
+
+use anyhow::{Context as _, Result};
+
+use super::{Hyprland, WorkspaceRef};
+
+static EVENT_SOCKET: &str = ".socket2.sock";
+
+/// The window events of the event socket, other events are skipped
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    OpenWindow {
+        address: String,
+        workspace: WorkspaceRef,
+        class: String,
+        title: String,
+    },
+    CloseWindow {
+        address: String,
+    },
+    WindowTitle {
+        address: String,
+        title: String,
+    },
+    MoveWindow {
+        address: String,
+        workspace: WorkspaceRef,
+    },
+    ChangeFloatingMode {
+        address: String,
+        floating: bool,
+    },
+    Pin {
+        address: String,
+        pinned: bool,
+    },
+}
+
+impl Event {
+    /// Parses a `name>>data` line.
+    /// Addresses are written without `0x` in events, it is added back to match the queries.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let (name, data) = line.split_once(">>")?;
+        let address = |a: &str| format!("0x{a}");
+        match name {
+            "openwindow" => {
+                // The title is last since it may contain commas
+                let mut fields = data.splitn(4, ',');
+                Some(Event::OpenWindow {
+                    address: address(fields.next()?),
+                    workspace: named_workspace(fields.next()?),
+                    class: fields.next()?.to_string(),
+                    title: fields.next().unwrap_or_default().to_string(),
+                })
+            }
+            "closewindow" => Some(Event::CloseWindow {
+                address: address(data),
+            }),
+            "windowtitlev2" => {
+                let (a, title) = data.split_once(',')?;
+                Some(Event::WindowTitle {
+                    address: address(a),
+                    title: title.to_string(),
+                })
+            }
+            "movewindowv2" => {
+                // The name is last since it may contain commas
+                let mut fields = data.splitn(3, ',');
+                Some(Event::MoveWindow {
+                    address: address(fields.next()?),
+                    workspace: WorkspaceRef {
+                        id: fields.next()?.parse().ok()?,
+                        name: fields.next()?.to_string(),
+                    },
+                })
+            }
+            "changefloatingmode" => {
+                let (a, floating) = data.split_once(',')?;
+                Some(Event::ChangeFloatingMode {
+                    address: address(a),
+                    floating: floating == "1",
+                })
+            }
+            "pin" => {
+                let (a, pinned) = data.split_once(',')?;
+                Some(Event::Pin {
+                    address: address(a),
+                    pinned: pinned == "1",
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// `openwindow` only names the workspace: numbered workspaces are named after their id,
+/// other names get no id so that they are selected by name
+fn named_workspace(name: &str) -> WorkspaceRef {
+    WorkspaceRef {
+        id: name.parse().unwrap_or(-1),
+        name: name.to_string(),
+    }
+}
+
+impl Hyprland {
+    /// Subscribes to the event socket, the iterator ends when the connection closes
+    ///
+    /// # Errors
+    /// Returns an error if the socket can not be reached.
+    pub fn events(&self) -> Result<impl Iterator<Item = Event> + use<>> {
+        let path = self.dir.join(EVENT_SOCKET);
+        let stream = UnixStream::connect(&path)
+            .with_context(|| format!("Failed to connect to {}", path.display()))?;
+        Ok(BufReader::new(stream)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| Event::parse(&line)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(id: i64, name: &str) -> WorkspaceRef {
+        WorkspaceRef {
+            id,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn open_window() {
+        assert_eq!(
+            Event::parse("openwindow>>55d0c6a3b2f0,2,firefox,Title, with commas"),
+            Some(Event::OpenWindow {
+                address: "0x55d0c6a3b2f0".to_string(),
+                workspace: workspace(2, "2"),
+                class: "firefox".to_string(),
+                title: "Title, with commas".to_string(),
+            })
+        );
+        let Some(Event::OpenWindow { workspace: ws, .. }) =
+            Event::parse("openwindow>>55d0c6a3b2f0,mail,thunderbird,Inbox")
+        else {
+            panic!("openwindow not parsed");
+        };
+        assert_eq!(ws.selector(), "name:mail");
+    }
+
+    #[test]
+    fn move_window() {
+        assert_eq!(
+            Event::parse("movewindowv2>>55d0c6a3b2f0,-98,special:scratchpad"),
+            Some(Event::MoveWindow {
+                address: "0x55d0c6a3b2f0".to_string(),
+                workspace: workspace(-98, "special:scratchpad"),
+            })
+        );
+        assert_eq!(
+            Event::parse("movewindowv2>>55d0c6a3b2f0,7,a,b"),
+            Some(Event::MoveWindow {
+                address: "0x55d0c6a3b2f0".to_string(),
+                workspace: workspace(7, "a,b"),
+            })
+        );
+        // The v1 event lacks the id, the v2 one is sent along
+        assert_eq!(Event::parse("movewindow>>55d0c6a3b2f0,7"), None);
+    }
+
+    #[test]
+    fn other_events() {
+        assert_eq!(
+            Event::parse("closewindow>>55d0c6a3b2f0"),
+            Some(Event::CloseWindow {
+                address: "0x55d0c6a3b2f0".to_string()
+            })
+        );
+        assert_eq!(
+            Event::parse("windowtitlev2>>55d0c6a3b2f0,a, b"),
+            Some(Event::WindowTitle {
+                address: "0x55d0c6a3b2f0".to_string(),
+                title: "a, b".to_string(),
+            })
+        );
+        assert_eq!(
+            Event::parse("changefloatingmode>>55d0c6a3b2f0,1"),
+            Some(Event::ChangeFloatingMode {
+                address: "0x55d0c6a3b2f0".to_string(),
+                floating: true,
+            })
+        );
+        assert_eq!(Event::parse("workspace>>2"), None);
+        assert_eq!(Event::parse("garbage"), None);
+    }
+}
@@ -0,0 +1,428 @@
+use std::{
+    fmt::Display,
+    io::{Read as _, Write as _},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::{Context as _, Result, bail};
+use serde::de::DeserializeOwned;
+
+use super::model::{Bind, Client, Layers, Monitor, Workspace};
+
+static REQUEST_SOCKET: &str = ".socket.sock";
+/// Hyprland separates the replies of a batch with this
+static BATCH_DELIMITER: &str = "\n\n\n";
+static TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Information requests, answered in JSON
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Query {
+    Clients,
+    ActiveWindow,
+    ActiveWorkspace,
+    Workspaces,
+    Monitors,
+    Layers,
+    Binds,
+}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Query::Clients => "clients",
+            Query::ActiveWindow => "activewindow",
+            Query::ActiveWorkspace => "activeworkspace",
+            Query::Workspaces => "workspaces",
+            Query::Monitors => "monitors",
+            Query::Layers => "layers",
+            Query::Binds => "binds",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Dispatchers, see `hyprctl dispatch`.
+/// Windows are selected by address, workspaces by anything `hyprctl` accepts (id, `name:`, `special:`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Dispatch {
+    FocusWindow {
+        address: String,
+    },
+    MoveToWorkspaceSilent {
+        workspace: String,
+        address: String,
+    },
+    /// Moves the focused window and follows it
+    MoveToWorkspace {
+        workspace: String,
+    },
+    Workspace {
+        workspace: String,
+    },
+    /// Shows or hides a special workspace by its name without the `special:` prefix
+    ToggleSpecialWorkspace {
+        name: String,
+    },
+    RenameWorkspace {
+        id: i64,
+        name: String,
+    },
+    /// Asks the window to close
+    CloseWindow {
+        address: String,
+    },
+    KillWindow {
+        address: String,
+    },
+    ToggleFloating {
+        address: String,
+    },
+    Pin {
+        address: String,
+    },
+    /// Toggles fullscreen on the focused window
+    Fullscreen,
+    /// Centers the focused window, if floating
+    CenterWindow,
+    /// Turns the focused window into a group, or ungroups it
+    ToggleGroup,
+    /// Moves the focused window into the group next to it, `l`, `r`, `u` or `d`
+    MoveIntoGroup {
+        direction: char,
+    },
+    /// Monitors are selected by name
+    FocusMonitor {
+        monitor: String,
+    },
+    MoveCurrentWorkspaceToMonitor {
+        monitor: String,
+    },
+    /// Turns the monitor off, or back on
+    ToggleDpms {
+        monitor: String,
+    },
+    /// Any dispatcher, as written in a bind
+    Raw {
+        dispatcher: String,
+        arg: String,
+    },
+}
+
+impl Display for Dispatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dispatch::FocusWindow { address } => write!(f, "focuswindow address:{address}"),
+            Dispatch::MoveToWorkspaceSilent { workspace, address } => {
+                write!(f, "movetoworkspacesilent {workspace},address:{address}")
+            }
+            Dispatch::MoveToWorkspace { workspace } => write!(f, "movetoworkspace {workspace}"),
+            Dispatch::Workspace { workspace } => write!(f, "workspace {workspace}"),
+            Dispatch::ToggleSpecialWorkspace { name } => {
+                write!(f, "togglespecialworkspace {name}")
+            }
+            Dispatch::RenameWorkspace { id, name } => write!(f, "renameworkspace {id} {name}"),
+            Dispatch::CloseWindow { address } => write!(f, "closewindow address:{address}"),
+            Dispatch::KillWindow { address } => write!(f, "killwindow address:{address}"),
+            Dispatch::ToggleFloating { address } => write!(f, "togglefloating address:{address}"),
+            Dispatch::Pin { address } => write!(f, "pin address:{address}"),
+            Dispatch::Fullscreen => write!(f, "fullscreen 0"),
+            Dispatch::CenterWindow => write!(f, "centerwindow"),
+            Dispatch::ToggleGroup => write!(f, "togglegroup"),
+            Dispatch::MoveIntoGroup { direction } => write!(f, "moveintogroup {direction}"),
+            Dispatch::FocusMonitor { monitor } => write!(f, "focusmonitor {monitor}"),
+            Dispatch::MoveCurrentWorkspaceToMonitor { monitor } => {
+                write!(f, "movecurrentworkspacetomonitor {monitor}")
+            }
+            Dispatch::ToggleDpms { monitor } => write!(f, "dpms toggle {monitor}"),
+            Dispatch::Raw { dispatcher, arg } => write!(f, "{dispatcher} {arg}"),
+        }
+    }
+}
+
+/// Client for the Hyprland request socket, one connection per request
+#[derive(Clone, Debug)]
+pub struct Hyprland {
+    /// Directory holding the instance sockets
+    pub(super) dir: PathBuf,
+}
+
+impl Hyprland {
+    /// Sockets of the instance in `dir`, a fake server can listen there
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The running instance, `$XDG_RUNTIME_DIR/hypr/$HYPRLAND_INSTANCE_SIGNATURE`
+    ///
+    /// # Errors
+    /// Returns an error if Hyprland is not running in this session.
+    pub fn from_env() -> Result<Self> {
+        let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+            .context("HYPRLAND_INSTANCE_SIGNATURE is not set, is Hyprland running?")?;
+        let runtime =
+            std::env::var_os("XDG_RUNTIME_DIR").map_or_else(std::env::temp_dir, PathBuf::from);
+        Ok(Self::new(runtime.join("hypr").join(signature)))
+    }
+
+    /// Sends a raw request and returns the raw reply
+    ///
+    /// # Errors
+    /// Returns an error if the socket can not be reached.
+    pub fn request(&self, request: &str) -> Result<String> {
+        let path = self.dir.join(REQUEST_SOCKET);
+        let mut stream = UnixStream::connect(&path)
+            .with_context(|| format!("Failed to connect to {}", path.display()))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        stream
+            .write_all(request.as_bytes())
+            .context("Failed to send Hyprland request")?;
+        // Hyprland closes the connection once it replied
+        let mut reply = String::new();
+        stream
+            .read_to_string(&mut reply)
+            .context("Failed to read Hyprland reply")?;
+        Ok(reply)
+    }
+
+    /// Runs a query and parses its JSON reply
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or the reply does not match `T`.
+    pub fn get<T: DeserializeOwned>(&self, query: Query) -> Result<T> {
+        let reply = self.request(&format!("j/{query}"))?;
+        serde_json::from_str(&reply).with_context(|| format!("Failed to parse {query} reply"))
+    }
+
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn clients(&self) -> Result<Vec<Client>> {
+        self.get(Query::Clients)
+    }
+
+    /// The focused window, if any
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn active_window(&self) -> Result<Option<Client>> {
+        // Hyprland replies `{}` when no window has the focus
+        let value: serde_json::Value = self.get(Query::ActiveWindow)?;
+        if value.as_object().is_some_and(serde_json::Map::is_empty) {
+            return Ok(None);
+        }
+        Ok(Some(
+            serde_json::from_value(value).context("Failed to parse activewindow reply")?,
+        ))
+    }
+
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn active_workspace(&self) -> Result<Workspace> {
+        self.get(Query::ActiveWorkspace)
+    }
+
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn workspaces(&self) -> Result<Vec<Workspace>> {
+        self.get(Query::Workspaces)
+    }
+
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn monitors(&self) -> Result<Vec<Monitor>> {
+        self.get(Query::Monitors)
+    }
+
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn layers(&self) -> Result<Layers> {
+        self.get(Query::Layers)
+    }
+
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub fn binds(&self) -> Result<Vec<Bind>> {
+        self.get(Query::Binds)
+    }
+
+    /// # Errors
+    /// Returns an error if the request fails or Hyprland rejects the dispatcher.
+    pub fn dispatch(&self, dispatch: &Dispatch) -> Result<()> {
+        let reply = self.request(&format!("dispatch {dispatch}"))?;
+        check_reply(dispatch, &reply)
+    }
+
+    /// Runs several dispatchers in a single request
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or Hyprland rejects any dispatcher.
+    pub fn batch(&self, dispatches: &[Dispatch]) -> Result<()> {
+        self.commands(dispatches.iter().map(|d| format!("dispatch {d}")))
+    }
+
+    /// Sets `keyword` to each of `values` in a single request, like `keyword monitor <value>`
+    ///
+    /// # Errors
+    /// Returns an error if the request fails or Hyprland rejects any value.
+    pub fn keywords(&self, keyword: &str, values: &[String]) -> Result<()> {
+        self.commands(values.iter().map(|v| format!("keyword {keyword} {v}")))
+    }
+
+    /// Sends commands answering `ok` as a batch
+    fn commands(&self, commands: impl Iterator<Item = String>) -> Result<()> {
+        let commands: Vec<String> = commands.collect();
+        if commands.is_empty() {
+            return Ok(());
+        }
+        let reply = self.request(&format!("[[BATCH]]{}", commands.join(";")))?;
+        // Some versions end the reply with the delimiter too
+        let replies: Vec<&str> = reply
+            .split(BATCH_DELIMITER)
+            .filter(|r| !r.trim().is_empty())
+            .collect();
+        if replies.len() == commands.len() {
+            return commands
+                .iter()
+                .zip(replies)
+                .try_for_each(|(command, reply)| check_reply(command, reply));
+        }
+        // Older versions concatenate the replies without any delimiter
+        if reply.split_whitespace().collect::<String>() == "ok".repeat(commands.len()) {
+            return Ok(());
+        }
+        bail!(
+            "Hyprland rejected `{}`: {}",
+            commands.join(";"),
+            reply.trim()
+        )
+    }
+}
+
+fn check_reply(command: &dyn Display, reply: &str) -> Result<()> {
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        bail!("Hyprland rejected `{command}`: {}", reply.trim())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{io::Read as _, os::unix::net::UnixListener, thread::JoinHandle};
+
+    use super::*;
+
+    /// A fake Hyprland answering each connection with the next of `replies`.
+    /// Joining the handle returns the requests it got.
+    pub(crate) fn serve(
+        replies: &[&str],
+    ) -> (tempfile::TempDir, Hyprland, JoinHandle<Vec<String>>) {
+        let dir = tempfile::tempdir().unwrap();
+        let listener = UnixListener::bind(dir.path().join(REQUEST_SOCKET)).unwrap();
+        let replies: Vec<String> = replies.iter().map(ToString::to_string).collect();
+        let server = std::thread::spawn(move || {
+            replies
+                .into_iter()
+                .map(|reply| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut buf = [0; 8192];
+                    let len = stream.read(&mut buf).unwrap();
+                    stream.write_all(reply.as_bytes()).unwrap();
+                    String::from_utf8_lossy(&buf[..len]).into_owned()
+                })
+                .collect()
+        });
+        let hypr = Hyprland::new(dir.path());
+        (dir, hypr, server)
+    }
+
+    fn focus(address: &str) -> Dispatch {
+        Dispatch::FocusWindow {
+            address: address.to_string(),
+        }
+    }
+
+    #[test]
+    fn single_requests() {
+        let (_dir, hypr, server) = serve(&["ok", "Invalid dispatcher", "{}"]);
+        hypr.dispatch(&focus("0x1")).unwrap();
+        let err = hypr
+            .dispatch(&Dispatch::Raw {
+                dispatcher: String::from("nope"),
+                arg: String::new(),
+            })
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Hyprland rejected `nope `: Invalid dispatcher"
+        );
+        assert!(hypr.active_window().unwrap().is_none());
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                "dispatch focuswindow address:0x1",
+                "dispatch nope ",
+                "j/activewindow"
+            ]
+        );
+    }
+
+    #[test]
+    fn batch_replies() {
+        let (_dir, hypr, server) = serve(&[
+            "ok\n\n\nok",
+            // Trailing delimiter
+            "ok\n\n\nok\n\n\n",
+            // No delimiter at all
+            "okok",
+            "ok\n\n\nNo such window found",
+            "ok",
+        ]);
+        let dispatches = [focus("0x1"), focus("0x2")];
+        hypr.batch(&dispatches).unwrap();
+        hypr.batch(&dispatches).unwrap();
+        hypr.batch(&dispatches).unwrap();
+        let err = hypr.batch(&dispatches).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Hyprland rejected `dispatch focuswindow address:0x2`: No such window found"
+        );
+        // A reply missing for one of the commands is an error
+        assert!(hypr.batch(&dispatches).is_err());
+        // Nothing to send, nothing sent
+        hypr.batch(&[]).unwrap();
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 5);
+        assert!(
+            requests.iter().all(|r| r
+                == "[[BATCH]]dispatch focuswindow address:0x1;dispatch focuswindow address:0x2")
+        );
+    }
+
+    #[test]
+    fn keywords() {
+        let (_dir, hypr, server) = serve(&["ok\n\n\nok\n\n\n"]);
+        hypr.keywords(
+            "monitor",
+            &[
+                String::from("DP-1,preferred,0x0,1"),
+                String::from("HDMI-A-1,disable"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            server.join().unwrap(),
+            ["[[BATCH]]keyword monitor DP-1,preferred,0x0,1;keyword monitor HDMI-A-1,disable"]
+        );
+    }
+
+    #[test]
+    fn unreachable_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = Hyprland::new(dir.path()).clients().unwrap_err();
+        assert!(err.to_string().starts_with("Failed to connect to"));
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+    thread,
+};
+
+use skim::{SkimItem, prelude::SkimItemSender};
+
+use super::{Client, events::Event};
+use crate::compositor::Compositor;
+
+/// A client kept up to date from the event socket, shared with the skim item showing it
+#[derive(Clone, Debug, Default)]
+pub struct LiveClient(Arc<RwLock<State>>);
+
+#[derive(Debug, Default)]
+struct State {
+    client: Client,
+    closed: bool,
+}
+
+impl LiveClient {
+    #[must_use]
+    pub fn new(client: Client) -> Self {
+        Self(Arc::new(RwLock::new(State {
+            client,
+            closed: false,
+        })))
+    }
+
+    /// A snapshot of the client
+    #[must_use]
+    pub fn client(&self) -> Client {
+        self.0.read().map(|s| s.client.clone()).unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.0.read().is_ok_and(|s| s.closed)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        if let Ok(mut state) = self.0.write() {
+            f(&mut state);
+        }
+    }
+}
+
+/// Sends the items for `clients`, then keeps them up to date while skim runs.
+/// Windows that open or move so that they match `filter` get a new item from `item`.
+pub fn stream(
+    compositor: &dyn Compositor,
+    clients: Vec<Client>,
+    tx: SkimItemSender,
+    filter: impl Fn(&Client) -> bool + Send + 'static,
+    item: impl Fn(LiveClient) -> Arc<dyn SkimItem> + Send + 'static,
+) {
+    let mut known: HashMap<String, LiveClient> = HashMap::new();
+    // Addresses that already have an item
+    let mut shown = HashSet::new();
+    for client in clients {
+        let live = LiveClient::new(client);
+        let address = live.client().address;
+        known.insert(address.clone(), live.clone());
+        if filter(&live.client()) {
+            shown.insert(address);
+            let _ = tx.send(item(live));
+        }
+    }
+    let events = match compositor.events() {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("Live updates disabled: {e:#}");
+            return;
+        }
+    };
+    thread::spawn(move || {
+        for event in events {
+            let address = match &event {
+                Event::OpenWindow { address, .. }
+                | Event::CloseWindow { address }
+                | Event::WindowTitle { address, .. }
+                | Event::MoveWindow { address, .. }
+                | Event::ChangeFloatingMode { address, .. }
+                | Event::Pin { address, .. } => address.clone(),
+            };
+            let live = known.entry(address.clone()).or_default();
+            live.update(|state| {
+                let client = &mut state.client;
+                client.address.clone_from(&address);
+                match event {
+                    Event::OpenWindow {
+                        workspace,
+                        class,
+                        title,
+                        ..
+                    } => {
+                        client.workspace = workspace;
+                        client.class = class;
+                        client.title = title;
+                        client.mapped = true;
+                    }
+                    Event::CloseWindow { .. } => state.closed = true,
+                    Event::WindowTitle { title, .. } => client.title = title,
+                    Event::MoveWindow { workspace, .. } => client.workspace = workspace,
+                    Event::ChangeFloatingMode { floating, .. } => client.floating = floating,
+                    Event::Pin { pinned, .. } => client.pinned = pinned,
+                }
+            });
+            // Windows unknown so far, or that did not match before, get their item now
+            if !shown.contains(&address) && !live.is_closed() && filter(&live.client()) {
+                shown.insert(address);
+                // The skim session is over once the receiver is gone
+                if tx.send(item(live.clone())).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,9 @@
+//! Shared Hyprland support for the `hyprctl-*` modes
+
+pub mod events;
+pub mod ipc;
+pub mod live;
+pub mod model;
+
+pub use ipc::{Dispatch, Hyprland, Query};
+pub use model::{Bind, Client, Layers, Monitor, Workspace, WorkspaceRef};
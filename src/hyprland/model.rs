@@ -0,0 +1,469 @@
+//! Replies of the Hyprland JSON queries.
+//! Fields missing from older Hyprland versions fall back to their default.
+
+// The structs mirror Hyprland's JSON, flags included
+#![allow(clippy::struct_excessive_bools)]
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A workspace as referenced from a client or a monitor
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct WorkspaceRef {
+    pub id: i64,
+    pub name: String,
+}
+
+impl WorkspaceRef {
+    /// Special workspaces (scratchpads) have negative ids
+    #[must_use]
+    pub fn is_special(&self) -> bool {
+        self.name.starts_with("special")
+    }
+
+    /// How dispatchers select this workspace, see [`Workspace::selector`]
+    #[must_use]
+    pub fn selector(&self) -> String {
+        selector(self.id, &self.name)
+    }
+}
+
+/// A window, from `clients` and `activewindow`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Client {
+    pub address: String,
+    pub mapped: bool,
+    pub hidden: bool,
+    /// Position, in layout coordinates
+    pub at: (i32, i32),
+    pub size: (i32, i32),
+    pub workspace: WorkspaceRef,
+    pub floating: bool,
+    pub pseudo: bool,
+    /// Monitor id
+    pub monitor: i64,
+    pub class: String,
+    pub title: String,
+    pub initial_class: String,
+    pub initial_title: String,
+    pub pid: i32,
+    pub xwayland: bool,
+    pub pinned: bool,
+    pub fullscreen: Fullscreen,
+    pub fullscreen_client: Fullscreen,
+    /// Addresses of the windows in the same group, this one included
+    pub grouped: Vec<String>,
+    pub tags: Vec<String>,
+    pub swallowing: String,
+    /// 0 for the focused window, then increasing with the time since last focus.
+    /// -1 for windows that were never focused.
+    #[serde(rename = "focusHistoryID")]
+    pub focus_history_id: i32,
+    pub inhibiting_idle: bool,
+}
+
+impl Client {
+    /// Every property, one `Name: value` per line
+    #[must_use]
+    pub fn properties(&self) -> String {
+        [
+            ("Title", self.title.clone()),
+            ("Class", self.class.clone()),
+            ("Initial title", self.initial_title.clone()),
+            ("Initial class", self.initial_class.clone()),
+            (
+                "Workspace",
+                format!("{} (id {})", self.workspace.name, self.workspace.id),
+            ),
+            ("Monitor", self.monitor.to_string()),
+            ("Address", self.address.clone()),
+            ("PID", self.pid.to_string()),
+            ("Position", format!("{},{}", self.at.0, self.at.1)),
+            ("Size", format!("{}x{}", self.size.0, self.size.1)),
+            ("Floating", self.floating.to_string()),
+            ("Pseudo", self.pseudo.to_string()),
+            ("Pinned", self.pinned.to_string()),
+            ("Fullscreen", format!("{:?}", self.fullscreen)),
+            ("Hidden", self.hidden.to_string()),
+            ("Mapped", self.mapped.to_string()),
+            ("Xwayland", self.xwayland.to_string()),
+            ("Grouped", self.grouped.join(", ")),
+            ("Tags", self.tags.join(", ")),
+            ("Swallowing", self.swallowing.clone()),
+            ("Focus history", self.focus_history_id.to_string()),
+            ("Inhibiting idle", self.inhibiting_idle.to_string()),
+        ]
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+}
+
+/// Fullscreen state, Hyprland used to report a plain bool
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(into = "u8")]
+pub enum Fullscreen {
+    #[default]
+    None,
+    Maximized,
+    Fullscreen,
+    /// Fullscreen on top of maximized
+    Both,
+}
+
+impl From<Fullscreen> for u8 {
+    fn from(f: Fullscreen) -> Self {
+        match f {
+            Fullscreen::None => 0,
+            Fullscreen::Maximized => 1,
+            Fullscreen::Fullscreen => 2,
+            Fullscreen::Both => 3,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Fullscreen {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Bool(bool),
+            Mode(u8),
+        }
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Bool(false) | Repr::Mode(0) => Fullscreen::None,
+            Repr::Mode(1) => Fullscreen::Maximized,
+            Repr::Bool(true) | Repr::Mode(2) => Fullscreen::Fullscreen,
+            Repr::Mode(_) => Fullscreen::Both,
+        })
+    }
+}
+
+/// From `workspaces` and `activeworkspace`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+    /// Monitor name
+    pub monitor: String,
+    #[serde(rename = "monitorID")]
+    pub monitor_id: i64,
+    /// Number of windows
+    pub windows: u32,
+    #[serde(rename = "hasfullscreen")]
+    pub has_fullscreen: bool,
+    /// Address of the last focused window
+    #[serde(rename = "lastwindow")]
+    pub last_window: String,
+    #[serde(rename = "lastwindowtitle")]
+    pub last_window_title: String,
+    #[serde(rename = "ispersistent")]
+    pub is_persistent: bool,
+}
+
+impl Workspace {
+    #[must_use]
+    pub fn is_special(&self) -> bool {
+        self.name.starts_with("special")
+    }
+
+    /// How dispatchers select this workspace, by id, `special:<name>`
+    /// or `name:<name>` when it has no id (Sway's named workspaces)
+    #[must_use]
+    pub fn selector(&self) -> String {
+        selector(self.id, &self.name)
+    }
+}
+
+fn selector(id: i64, name: &str) -> String {
+    if name.starts_with("special") {
+        name.to_string()
+    } else if id < 0 {
+        format!("name:{name}")
+    } else {
+        id.to_string()
+    }
+}
+
+/// From `monitors`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Monitor {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub make: String,
+    pub model: String,
+    pub serial: String,
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: f64,
+    pub x: i32,
+    pub y: i32,
+    pub active_workspace: WorkspaceRef,
+    pub special_workspace: WorkspaceRef,
+    /// Space reserved by bars, left, top, right, bottom
+    pub reserved: (i32, i32, i32, i32),
+    pub scale: f64,
+    pub transform: u8,
+    pub focused: bool,
+    pub dpms_status: bool,
+    pub vrr: bool,
+    pub disabled: bool,
+    pub current_format: String,
+    pub mirror_of: String,
+    /// Like `2560x1440@143.99Hz`
+    pub available_modes: Vec<String>,
+}
+
+/// Layer surfaces of every monitor, by monitor name, from `layers`
+pub type Layers = BTreeMap<String, MonitorLayers>;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct MonitorLayers {
+    /// Surfaces by level, "0" (background) to "3" (overlay)
+    pub levels: BTreeMap<String, Vec<Layer>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Layer {
+    pub address: String,
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    pub namespace: String,
+    pub pid: i32,
+}
+
+/// A key or mouse binding, from `binds`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Bind {
+    pub locked: bool,
+    pub mouse: bool,
+    pub release: bool,
+    pub repeat: bool,
+    #[serde(rename = "longPress")]
+    pub long_press: bool,
+    pub non_consuming: bool,
+    pub has_description: bool,
+    /// Modifier bit mask, see [`Bind::modifiers`]
+    pub modmask: u32,
+    pub submap: String,
+    pub key: String,
+    pub keycode: i32,
+    pub catch_all: bool,
+    pub description: String,
+    pub dispatcher: String,
+    pub arg: String,
+}
+
+impl Bind {
+    /// Names of the modifiers in `modmask`, like `SUPER` and `SHIFT`
+    #[must_use]
+    pub fn modifiers(&self) -> Vec<&'static str> {
+        [
+            (1 << 6, "SUPER"),
+            (1 << 3, "ALT"),
+            (1 << 2, "CTRL"),
+            (1 << 0, "SHIFT"),
+            (1 << 1, "CAPS"),
+            (1 << 4, "MOD2"),
+            (1 << 5, "MOD3"),
+            (1 << 7, "MOD5"),
+        ]
+        .into_iter()
+        .filter(|(bit, _)| self.modmask & bit != 0)
+        .map(|(_, name)| name)
+        .collect()
+    }
+
+    /// The modifiers and key, like `SUPER+SHIFT+Q`
+    #[must_use]
+    pub fn combo(&self) -> String {
+        let key = if self.key.is_empty() {
+            format!("code:{}", self.keycode)
+        } else if self.key.chars().count() == 1 {
+            self.key.to_uppercase()
+        } else {
+            self.key.clone()
+        };
+        let mut parts = self.modifiers();
+        parts.push(&key);
+        parts.join("+")
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use serde::{Serialize, de::DeserializeOwned};
+    use serde_json::Value;
+
+    use super::*;
+
+    /// A reply recorded from `hyprctl <name> -j`
+    pub(crate) fn fixture(name: &str) -> String {
+        let path = format!(
+            "{}/tests/fixtures/hyprland/{name}.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"))
+    }
+
+    /// Every field we serialize has the recorded value, fields we do not model are ignored
+    fn assert_recorded(ours: &Value, recorded: &Value, path: &str) {
+        match (ours, recorded) {
+            (Value::Object(ours), Value::Object(recorded)) => {
+                for (key, value) in ours {
+                    let recorded = recorded
+                        .get(key)
+                        .unwrap_or_else(|| panic!("{path}.{key} is not in the recording"));
+                    assert_recorded(value, recorded, &format!("{path}.{key}"));
+                }
+            }
+            (Value::Array(ours), Value::Array(recorded)) => {
+                assert_eq!(ours.len(), recorded.len(), "{path}");
+                for (i, (ours, recorded)) in ours.iter().zip(recorded).enumerate() {
+                    assert_recorded(ours, recorded, &format!("{path}[{i}]"));
+                }
+            }
+            _ => assert_eq!(ours, recorded, "{path}"),
+        }
+    }
+
+    /// Deserializes the fixture, serializes it back and checks both directions agree
+    fn round_trip<T: DeserializeOwned + Serialize + PartialEq + std::fmt::Debug>(
+        name: &str,
+    ) -> (T, Value) {
+        let recorded = fixture(name);
+        let parsed: T = serde_json::from_str(&recorded).unwrap();
+        let serialized = serde_json::to_value(&parsed).unwrap();
+        let reparsed: T = serde_json::from_value(serialized.clone()).unwrap();
+        assert_eq!(parsed, reparsed);
+        (parsed, serialized)
+    }
+
+    fn assert_round_trip<T: DeserializeOwned + Serialize + PartialEq + std::fmt::Debug>(
+        name: &str,
+    ) -> T {
+        let (parsed, serialized) = round_trip::<T>(name);
+        let recorded: Value = serde_json::from_str(&fixture(name)).unwrap();
+        assert_recorded(&serialized, &recorded, name);
+        parsed
+    }
+
+    #[test]
+    fn clients() {
+        let clients: Vec<Client> = assert_round_trip("clients");
+        assert_eq!(clients.len(), 3);
+        let [firefox, kitty, keepass] = &clients[..] else {
+            unreachable!()
+        };
+        assert_eq!(firefox.fullscreen, Fullscreen::None);
+        assert_eq!(kitty.fullscreen, Fullscreen::Maximized);
+        assert_eq!(kitty.fullscreen_client, Fullscreen::Fullscreen);
+        assert_eq!(keepass.fullscreen, Fullscreen::Both);
+        assert_eq!(kitty.grouped, ["0x55d0c6b41a80", "0x55d0c6b77c10"]);
+        assert_eq!(kitty.focus_history_id, 0);
+        assert_eq!(keepass.focus_history_id, -1);
+        assert!(keepass.workspace.is_special());
+        assert_eq!(keepass.workspace.selector(), "special:scratchpad");
+        assert_eq!(firefox.workspace.selector(), "1");
+        assert_eq!(firefox.at, (1288, 52));
+    }
+
+    #[test]
+    fn clients_with_bool_fullscreen() {
+        // Hyprland before 0.42 reported `fullscreen` as a bool
+        let (clients, serialized) = round_trip::<Vec<Client>>("clients_bool_fullscreen");
+        assert_eq!(clients[0].fullscreen, Fullscreen::Fullscreen);
+        assert_eq!(clients[1].fullscreen, Fullscreen::None);
+        // Missing fields fall back to their default
+        assert_eq!(clients[0].fullscreen_client, Fullscreen::None);
+        assert!(clients[0].tags.is_empty());
+        // Written back in the current, numeric form
+        assert_eq!(serialized[0]["fullscreen"], 2);
+        assert_eq!(serialized[1]["fullscreen"], 0);
+    }
+
+    #[test]
+    fn fullscreen_forms() {
+        let parse = |json: &str| serde_json::from_str::<Fullscreen>(json).unwrap();
+        assert_eq!(parse("false"), Fullscreen::None);
+        assert_eq!(parse("true"), Fullscreen::Fullscreen);
+        assert_eq!(parse("0"), Fullscreen::None);
+        assert_eq!(parse("1"), Fullscreen::Maximized);
+        assert_eq!(parse("2"), Fullscreen::Fullscreen);
+        assert_eq!(parse("3"), Fullscreen::Both);
+        for fullscreen in [
+            Fullscreen::None,
+            Fullscreen::Maximized,
+            Fullscreen::Fullscreen,
+            Fullscreen::Both,
+        ] {
+            let json = serde_json::to_string(&fullscreen).unwrap();
+            assert_eq!(parse(&json), fullscreen);
+        }
+        assert!(serde_json::from_str::<Fullscreen>("\"yes\"").is_err());
+    }
+
+    #[test]
+    fn workspaces() {
+        let workspaces: Vec<Workspace> = assert_round_trip("workspaces");
+        let selectors: Vec<String> = workspaces.iter().map(Workspace::selector).collect();
+        assert_eq!(selectors, ["1", "4", "special:scratchpad"]);
+        assert!(workspaces[0].has_fullscreen);
+        assert!(workspaces[1].is_persistent);
+        assert!(workspaces[2].is_special());
+    }
+
+    #[test]
+    fn monitors() {
+        let monitors: Vec<Monitor> = assert_round_trip("monitors");
+        assert_eq!(monitors[0].reserved, (0, 32, 0, 0));
+        assert!((monitors[0].refresh_rate - 119.998).abs() < f64::EPSILON);
+        assert!((monitors[1].scale - 1.5).abs() < f64::EPSILON);
+        assert_eq!(monitors[1].special_workspace.name, "special:scratchpad");
+        assert!(!monitors[1].dpms_status);
+        assert_eq!(monitors[1].available_modes.len(), 2);
+    }
+
+    #[test]
+    fn layers() {
+        let layers: Layers = assert_round_trip("layers");
+        assert_eq!(
+            layers.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["DP-1", "HDMI-A-1"]
+        );
+        let bar = &layers["DP-1"].levels["2"][0];
+        assert_eq!((bar.namespace.as_str(), bar.h), ("waybar", 32));
+        assert!(layers["HDMI-A-1"].levels["1"].is_empty());
+    }
+
+    #[test]
+    fn binds() {
+        let binds: Vec<Bind> = assert_round_trip("binds");
+        let combos: Vec<String> = binds.iter().map(Bind::combo).collect();
+        assert_eq!(
+            combos,
+            [
+                "SUPER+Return",
+                "SUPER+SHIFT+Q",
+                "XF86AudioRaiseVolume",
+                "SUPER+mouse:272",
+                "code:113",
+            ]
+        );
+        assert!(binds[2].locked && binds[2].repeat);
+        assert!(binds[3].mouse);
+        assert_eq!(binds[4].submap, "resize");
+    }
+}
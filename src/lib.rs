@@ -4,23 +4,40 @@
 use anyhow::Result;
 use std::sync::Arc;
 
-use skim::{SkimItem, SkimOutput};
+use skim::{SkimItem, SkimOutput, prelude::SkimItemSender};
 
 #[cfg(feature = "apps")]
 pub mod apps;
 #[cfg(feature = "calc")]
 pub mod calc;
 pub mod cli;
+#[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
+pub mod compositor;
 #[cfg(feature = "hyprland")]
+pub mod hyprctl_binds;
+#[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
 pub mod hyprctl_clients;
-#[cfg(feature = "hyprland")]
+#[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
 pub mod hyprctl_hide;
+#[cfg(feature = "hyprland")]
+pub mod hyprctl_monitors;
+#[cfg(feature = "hyprland")]
+pub mod hyprctl_workspaces;
+#[cfg(any(feature = "hyprland", feature = "sway", feature = "niri"))]
+pub mod hyprland;
 #[cfg(feature = "paru")]
 pub mod paru;
 #[cfg(feature = "systemd")]
 pub mod systemd_services;
+pub mod xdg;
 pub use cli::*;
 
+/// Path to the running binary, used to re-exec ourselves from skim bindings
+#[must_use]
+pub fn current_exe() -> String {
+    std::env::current_exe().map_or_else(|_| "skim-run".to_string(), |p| p.display().to_string())
+}
+
 pub trait SkimRun {
     //! Init the runner
     //! Will return false if we should stop here, or true if the skim instance should be started
@@ -34,6 +51,14 @@ pub trait SkimRun {
         Vec::new()
     }
 
+    /// Send the items to skim, by default those of `get`.
+    /// Modes can keep `tx` to add items while skim runs.
+    fn stream(&self, tx: SkimItemSender) {
+        for item in self.get() {
+            let _ = tx.send(item);
+        }
+    }
+
     /// Set `SkimOptions`
     fn set_options(&self, opts: &mut skim::SkimOptions) {
         let _ = opts;
@@ -38,10 +38,7 @@ fn run_with(mode: &dyn SkimRun, args: &Cli) -> Result<Option<String>> {
             options.header = Some(format!("next mode(tab): {next_mode}"));
         }
     }
-    for item in mode.get() {
-        let _ = tx_item.send(item);
-    }
-    drop(tx_item);
+    mode.stream(tx_item);
 
     let Some(output) = Skim::run_with(&options, Some(rx_item)) else {
         return Ok(None);
@@ -60,7 +57,7 @@ fn run_with(mode: &dyn SkimRun, args: &Cli) -> Result<Option<String>> {
             return Ok(Some(next_args));
         }
         Event::EvActAccept(None) => {
-            let _ = mode.run(&output);
+            mode.run(&output)?;
             return Ok(None);
         }
         _ => {
@@ -1,16 +1,164 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use skim::SkimItem;
-use zbus_systemd::{zbus::Connection, zvariant::OwnedObjectPath};
+use anyhow::{Context as _, Result, bail};
+use rustix::time::{ClockId, clock_gettime};
+use skim::{SkimItem, prelude::SkimItemSender};
+use smol::stream::StreamExt as _;
+use zbus_systemd::{
+    systemd1::{ManagerProxy, MountProxy, PathProxy, SocketProxy, TimerProxy},
+    zbus::{Connection, proxy::MethodFlags},
+    zvariant::{DynamicDeserialize, DynamicType, OwnedObjectPath},
+};
 
-use crate::SkimRun;
+use crate::{SkimRun, xdg};
 
-struct SystemdService {
+/// How often the list is refreshed while skim runs
+static REFRESH: Duration = Duration::from_secs(1);
+/// How long an action waits for its job before giving up on reporting it
+static JOB_TIMEOUT: Duration = Duration::from_secs(90);
+/// Last action result of each unit, a file per unit read by the running picker
+static RESULTS_DIR: &str = "systemd-results";
+/// Splits the lines into fields, `{2}` is the bus and `{4}` the unit name
+static DELIMITER: &str = r"[\[\] \t]+";
+
+/// The systemd instance managing a unit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum Bus {
+    System,
+    User,
+}
+
+impl Bus {
+    fn name(self) -> &'static str {
+        match self {
+            Bus::System => "system",
+            Bus::User => "user",
+        }
+    }
+
+    async fn manager(self) -> Result<ManagerProxy<'static>> {
+        let conn = match self {
+            Bus::System => Connection::system().await,
+            Bus::User => Connection::session().await,
+        }
+        .with_context(|| format!("Failed to connect to the {} bus", self.name()))?;
+        Ok(ManagerProxy::new(&conn).await?)
+    }
+}
+
+/// Actions on the selected unit, run as systemd jobs or unit file changes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum UnitAction {
+    Start,
+    Stop,
+    Restart,
+    Reload,
+    Enable,
+    Disable,
+    Mask,
+    Unmask,
+    ResetFailed,
+}
+
+impl UnitAction {
+    fn name(self) -> &'static str {
+        match self {
+            UnitAction::Start => "start",
+            UnitAction::Stop => "stop",
+            UnitAction::Restart => "restart",
+            UnitAction::Reload => "reload",
+            UnitAction::Enable => "enable",
+            UnitAction::Disable => "disable",
+            UnitAction::Mask => "mask",
+            UnitAction::Unmask => "unmask",
+            UnitAction::ResetFailed => "reset-failed",
+        }
+    }
+
+    /// The manager method running the action
+    fn method(self) -> &'static str {
+        match self {
+            UnitAction::Start => "StartUnit",
+            UnitAction::Stop => "StopUnit",
+            UnitAction::Restart => "RestartUnit",
+            UnitAction::Reload => "ReloadUnit",
+            UnitAction::Enable => "EnableUnitFiles",
+            UnitAction::Disable => "DisableUnitFiles",
+            UnitAction::Mask => "MaskUnitFiles",
+            UnitAction::Unmask => "UnmaskUnitFiles",
+            UnitAction::ResetFailed => "ResetFailedUnit",
+        }
+    }
+}
+
+/// Unit types, from the suffix of their name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum UnitType {
+    Service,
+    Socket,
+    Target,
+    Device,
+    Mount,
+    Automount,
+    Swap,
+    Timer,
+    Path,
+    Slice,
+    Scope,
+}
+
+impl UnitType {
+    const ALL: [UnitType; 11] = [
+        UnitType::Service,
+        UnitType::Socket,
+        UnitType::Target,
+        UnitType::Device,
+        UnitType::Mount,
+        UnitType::Automount,
+        UnitType::Swap,
+        UnitType::Timer,
+        UnitType::Path,
+        UnitType::Slice,
+        UnitType::Scope,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            UnitType::Service => "service",
+            UnitType::Socket => "socket",
+            UnitType::Target => "target",
+            UnitType::Device => "device",
+            UnitType::Mount => "mount",
+            UnitType::Automount => "automount",
+            UnitType::Swap => "swap",
+            UnitType::Timer => "timer",
+            UnitType::Path => "path",
+            UnitType::Slice => "slice",
+            UnitType::Scope => "scope",
+        }
+    }
+
+    fn of(unit: &str) -> Option<Self> {
+        let (_, suffix) = unit.rsplit_once('.')?;
+        Self::ALL.into_iter().find(|t| t.name() == suffix)
+    }
+}
+
+/// A unit, from `ListUnits`
+#[derive(Clone, Debug)]
+struct Unit {
     name: String,
     desc: String,
     load_state: String,
     active_state: String,
-    is_user: bool,
     sub_state: String,
     follower: String,
     object_path: OwnedObjectPath,
@@ -19,81 +167,380 @@ struct SystemdService {
     job_object_path: OwnedObjectPath,
 }
 
-impl SkimItem for SystemdService {
-    fn text(&self) -> Cow<str> {
-        Cow::Owned(format!(
-            "[{}] {} {} {}",
-            if self.is_user { "user" } else { "system" },
-            self.name,
-            self.load_state,
-            self.active_state
-        ))
+/// A unit kept up to date while skim runs, shared with the skim item showing it
+#[derive(Debug)]
+struct UnitState {
+    unit: Unit,
+    /// Still loaded, stopped units can drop out of the list
+    listed: bool,
+    /// Outcome of the last action on the unit
+    result: Option<String>,
+}
+
+struct SystemdUnit {
+    bus: Bus,
+    /// Connection to `bus`, for the details of the preview
+    conn: Connection,
+    state: Arc<RwLock<UnitState>>,
+}
+
+impl SystemdUnit {
+    fn unit(&self) -> Unit {
+        self.state
+            .read()
+            .map(|s| s.unit.clone())
+            .expect("Poisoned unit state")
+    }
+}
+
+impl UnitState {
+    /// The line of the unit, split into fields by [`DELIMITER`]
+    fn line(&self, bus: Bus) -> String {
+        format!(
+            "[{}] [{}] {} {} {}{}{}",
+            bus.name(),
+            UnitType::of(&self.unit.name).map_or("unknown", UnitType::name),
+            self.unit.name,
+            self.unit.load_state,
+            self.unit.active_state,
+            if self.listed { "" } else { " (unloaded)" },
+            self.result
+                .as_ref()
+                .map(|r| format!(" - {r}"))
+                .unwrap_or_default()
+        )
+    }
+}
+
+impl SkimItem for SystemdUnit {
+    fn text(&self) -> Cow<'_, str> {
+        let Ok(state) = self.state.read() else {
+            return Cow::Borrowed("");
+        };
+        Cow::Owned(state.line(self.bus))
     }
     fn preview(&self, _context: skim::PreviewContext) -> skim::ItemPreview {
+        let unit = self.unit();
+        let mut info = vec![
+            format!("Name: {}", unit.name),
+            format!("Description: {}", unit.desc),
+            format!(
+                "States: {}, {}, {}",
+                unit.load_state, unit.active_state, unit.sub_state
+            ),
+            format!("Path: {}", unit.object_path.as_str()),
+        ];
+        if !unit.follower.is_empty() {
+            info.push(format!("Follower: {}", unit.follower));
+        }
+        if unit.job_id != 0 {
+            info.push(format!(
+                "Job: id {}, type {}, path {}",
+                unit.job_id,
+                unit.job_type,
+                unit.job_object_path.as_str()
+            ));
+        }
+        match smol::block_on(details(&self.conn, &unit)) {
+            Ok(details) if !details.is_empty() => {
+                info.push(String::new());
+                info.extend(details);
+            }
+            Ok(_) => {}
+            Err(e) => info.push(format!("\nDetails unavailable: {e:#}")),
+        }
+        let info = info.join("\n");
         skim::ItemPreview::Command(format!(
-            "
-echo Name: {}
-echo Description: {}
-echo States: {}, {}, {}
-echo Path: {}
-if [ ! -z {} ]; then echo Follower: {}; fi
-if [ -ne {} 0 ]; then echo; echo Job: id {}, type {}, path {}; fi
+            "printf '%s\\n' {}
 echo
 echo --- logs ---
 echo
 journalctl --{} -xreu {}
 ",
-            self.name.clone(),
-            self.desc.clone(),
-            self.load_state.clone(),
-            self.active_state.clone(),
-            self.sub_state.clone(),
-            self.object_path.clone(),
-            self.follower.clone(),
-            self.follower.clone(),
-            self.job_id,
-            self.job_id,
-            self.job_type.clone(),
-            self.job_object_path.clone(),
-            if self.is_user { "user" } else { "system" },
-            self.name.clone(),
+            shlex::try_quote(&info).unwrap_or_default(),
+            self.bus.name(),
+            // Escaped names like `dev-disk-by\x2dlabel-data.device` keep their backslashes
+            shlex::try_quote(&unit.name).unwrap_or_default(),
         ))
     }
 }
 
-pub struct SystemdServices;
+/// Lines specific to the type of `unit`: triggers of timers, listen addresses of sockets,
+/// what is mounted where, watched paths
+async fn details(conn: &Connection, unit: &Unit) -> Result<Vec<String>> {
+    let path = unit.object_path.clone();
+    Ok(match UnitType::of(&unit.name) {
+        Some(UnitType::Timer) => {
+            let timer = TimerProxy::builder(conn).path(path)?.build().await?;
+            let now = Clocks::now();
+            // The sooner of the calendar and the monotonic (`OnBootSec=`, …) triggers,
+            // each is 0 when the timer has none
+            let next = [
+                timer.next_elapse_u_sec_realtime().await?,
+                monotonic_to_realtime(timer.next_elapse_u_sec_monotonic().await?, now),
+            ]
+            .into_iter()
+            .filter(|usec| *usec > 0)
+            .min()
+            .unwrap_or_default();
+            let mut lines = vec![
+                format!("Next trigger: {}", relative_time(next, now)),
+                format!(
+                    "Last trigger: {}",
+                    relative_time(timer.last_trigger_u_sec().await?, now)
+                ),
+                format!("Triggers: {}", timer.unit().await?),
+            ];
+            lines.extend(
+                timer
+                    .timers_calendar()
+                    .await?
+                    .into_iter()
+                    .map(|(base, spec, _)| format!("{base}: {spec}")),
+            );
+            lines
+        }
+        Some(UnitType::Socket) => {
+            let socket = SocketProxy::builder(conn).path(path)?.build().await?;
+            let mut lines: Vec<String> = socket
+                .listen()
+                .await?
+                .into_iter()
+                .map(|(kind, address)| format!("Listen ({kind}): {address}"))
+                .collect();
+            lines.push(format!(
+                "Connections: {}, accepted: {}",
+                socket.n_connections().await?,
+                socket.n_accepted().await?
+            ));
+            lines
+        }
+        Some(UnitType::Mount) => {
+            let mount = MountProxy::builder(conn).path(path)?.build().await?;
+            vec![
+                format!("What: {}", mount.what().await?),
+                format!("Where: {}", mount.where_property().await?),
+                format!("Type: {}", mount.type_property().await?),
+                format!("Options: {}", mount.options().await?),
+            ]
+        }
+        Some(UnitType::Path) => {
+            let watch = PathProxy::builder(conn).path(path)?.build().await?;
+            let mut lines: Vec<String> = watch
+                .paths()
+                .await?
+                .into_iter()
+                .map(|(condition, path)| format!("{condition}: {path}"))
+                .collect();
+            lines.push(format!("Triggers: {}", watch.unit().await?));
+            lines
+        }
+        _ => Vec::new(),
+    })
+}
 
-fn bind_systemctl(key: &str, cmd: &str) -> String {
-    format!("{key}:execute(systemctl {cmd} --{{2}} {{3}})+accept(--query {{q}} systemd-services)")
+/// The current time of the realtime and monotonic clocks, in microseconds
+#[derive(Clone, Copy, Debug)]
+struct Clocks {
+    realtime: u64,
+    monotonic: u64,
 }
 
-impl SkimRun for SystemdServices {
+impl Clocks {
+    fn now() -> Self {
+        let monotonic = clock_gettime(ClockId::Monotonic);
+        Self {
+            realtime: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| u64::try_from(d.as_micros()).unwrap_or_default()),
+            monotonic: u64::try_from(monotonic.tv_sec).unwrap_or_default() * 1_000_000
+                + u64::try_from(monotonic.tv_nsec).unwrap_or_default() / 1_000,
+        }
+    }
+}
+
+/// The realtime timestamp of a monotonic one, both in microseconds and 0 when unset
+fn monotonic_to_realtime(usec: u64, now: Clocks) -> u64 {
+    if usec == 0 {
+        return 0;
+    }
+    (now.realtime + usec).saturating_sub(now.monotonic)
+}
+
+/// A realtime timestamp in microseconds relative to now, like `in 2h 5m` or `3d 1h ago`
+fn relative_time(usec: u64, now: Clocks) -> String {
+    if usec == 0 {
+        return String::from("n/a");
+    }
+    let now = now.realtime / 1_000_000;
+    let secs = usec / 1_000_000;
+    let span = |s: u64| {
+        let (d, h, m) = (s / 86_400, s / 3_600 % 24, s / 60 % 60);
+        match (d, h, m) {
+            (0, 0, 0) => format!("{s}s"),
+            (0, 0, m) => format!("{m}m"),
+            (0, h, m) => format!("{h}h {m}m"),
+            (d, h, _) => format!("{d}d {h}h"),
+        }
+    };
+    if secs >= now {
+        format!("in {}", span(secs - now))
+    } else {
+        format!("{} ago", span(now - secs))
+    }
+}
+
+/// Mode listing the system and user units of `types`, all of them when empty.
+/// Actions run over D-Bus from the bindings, the list follows their outcome.
+pub struct SystemdUnits {
+    pub types: Vec<UnitType>,
+}
+
+/// Runs `action` on the selected unit, `{2}` being its bus and `{4}` its name
+fn bind_action(key: &str, action: UnitAction) -> String {
+    format!(
+        "{key}:execute-silent({} systemd-services --action {} --bus {{2}} --unit {{4}})",
+        crate::current_exe(),
+        action.name()
+    )
+}
+
+impl SkimRun for SystemdUnits {
+    fn init(&self, mode: &crate::Mode) -> bool {
+        let crate::Mode::SystemdServices {
+            action: Some(action),
+            unit: Some(unit),
+            bus,
+        } = mode
+        else {
+            return true;
+        };
+        let result = smol::block_on(act(*bus, unit, *action));
+        let message = match &result {
+            Ok(message) => message.clone(),
+            Err(e) => format!("{} failed: {e:#}", action.name()),
+        };
+        eprintln!("[systemd-services] {unit}: {message}");
+        if let Err(e) = save_result(&results_dir(), *bus, unit, &message) {
+            eprintln!("[systemd-services] {e:#}");
+        }
+        false
+    }
+
     fn set_options(&self, opts: &mut skim::SkimOptions) {
         opts.preview = Some(String::new());
         opts.preview_window = String::from("up:80%");
-        opts.delimiter = String::from(r"[\[\] \t]+");
+        opts.delimiter = String::from(DELIMITER);
         opts.header = Some(String::from(
-            "systemctl - restart: ^r | stop: ^s | start: ^t",
+            "restart: ^r | stop: ^s | start: ^t | reload: alt-r | enable: alt-e | disable: alt-x | mask: alt-m | unmask: alt-u | reset failed: alt-c",
         ));
         opts.bind.extend(vec![
-            bind_systemctl("ctrl-r", "restart"),
-            bind_systemctl("ctrl-s", "stop"),
-            bind_systemctl("ctrl-s", "start"),
+            bind_action("ctrl-r", UnitAction::Restart),
+            bind_action("ctrl-s", UnitAction::Stop),
+            bind_action("ctrl-t", UnitAction::Start),
+            bind_action("alt-r", UnitAction::Reload),
+            bind_action("alt-e", UnitAction::Enable),
+            bind_action("alt-x", UnitAction::Disable),
+            bind_action("alt-m", UnitAction::Mask),
+            bind_action("alt-u", UnitAction::Unmask),
+            bind_action("alt-c", UnitAction::ResetFailed),
         ]);
     }
-    fn get(&self) -> Vec<std::sync::Arc<dyn SkimItem>> {
-        let system_units: Vec<Arc<dyn SkimItem>> = smol::block_on(async {
-            let conn = Connection::system()
-                .await
-                .expect("Failed to connect to system bus");
-            let manager = zbus_systemd::systemd1::ManagerProxy::new(&conn)
-                .await
-                .unwrap();
-            manager.list_units().await.clone()
-        })
-        .expect("Failed to list systemd units")
+
+    fn stream(&self, tx: SkimItemSender) {
+        // Results of an earlier session would be stale
+        let _ = fs::remove_dir_all(results_dir());
+        // Buses that can not be reached are left out
+        let managers: Vec<(Bus, ManagerProxy<'static>)> = [Bus::System, Bus::User]
+            .into_iter()
+            .filter_map(|bus| match smol::block_on(bus.manager()) {
+                Ok(manager) => Some((bus, manager)),
+                Err(e) => {
+                    eprintln!("[systemd-services] {e:#}");
+                    None
+                }
+            })
+            .collect();
+        let mut known: HashMap<(Bus, String), Arc<RwLock<UnitState>>> = HashMap::new();
+        let types = self.types.clone();
+        refresh(&managers, &types, &mut known, &tx);
+        thread::spawn(move || {
+            loop {
+                thread::sleep(REFRESH);
+                if !refresh(&managers, &types, &mut known, &tx) {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+/// Lists the units of `types` on each bus, updating the known ones and sending the new ones.
+/// Returns false once skim is gone.
+fn refresh(
+    managers: &[(Bus, ManagerProxy<'static>)],
+    types: &[UnitType],
+    known: &mut HashMap<(Bus, String), Arc<RwLock<UnitState>>>,
+    tx: &SkimItemSender,
+) -> bool {
+    let results = load_results(&results_dir());
+    for (bus, manager) in managers {
+        let bus = *bus;
+        // Errors would be repeated every refresh, the list just stays as it is
+        let Ok(units) = smol::block_on(list_units(manager)) else {
+            continue;
+        };
+        let mut listed: Vec<String> = Vec::with_capacity(units.len());
+        let wanted = |unit: &Unit| {
+            types.is_empty() || UnitType::of(&unit.name).is_some_and(|t| types.contains(&t))
+        };
+        for unit in units.into_iter().filter(wanted) {
+            let key = (bus, unit.name.clone());
+            listed.push(unit.name.clone());
+            if let Some(state) = known.get(&key) {
+                if let Ok(mut state) = state.write() {
+                    state.unit = unit;
+                    state.listed = true;
+                }
+                continue;
+            }
+            let state = Arc::new(RwLock::new(UnitState {
+                unit,
+                listed: true,
+                result: None,
+            }));
+            known.insert(key, state.clone());
+            let item = SystemdUnit {
+                bus,
+                conn: manager.inner().connection().clone(),
+                state,
+            };
+            if tx.send(Arc::new(item)).is_err() {
+                return false;
+            }
+        }
+        for ((unit_bus, name), state) in known.iter() {
+            if *unit_bus != bus {
+                continue;
+            }
+            if let Ok(mut state) = state.write() {
+                state.listed = listed.contains(name);
+                state.result = results
+                    .get(&(bus.name().to_string(), name.clone()))
+                    .cloned();
+            }
+        }
+    }
+    true
+}
+
+async fn list_units(manager: &ManagerProxy<'_>) -> Result<Vec<Unit>> {
+    Ok(manager
+        .list_units()
+        .await
+        .context("Failed to list the units")?
         .into_iter()
-        .filter_map(
+        .map(
             |(
                 name,
                 desc,
@@ -105,40 +552,7 @@ impl SkimRun for SystemdServices {
                 job_id,
                 job_type,
                 job_object_path,
-            )| {
-                if name.ends_with(".service") {
-                    Some(Arc::new(SystemdService {
-                        name,
-                        desc,
-                        load_state,
-                        is_user: false,
-                        active_state,
-                        sub_state,
-                        follower,
-                        object_path,
-                        job_id,
-                        job_type,
-                        job_object_path,
-                    }) as Arc<dyn SkimItem>)
-                } else {
-                    None
-                }
-            },
-        )
-        .collect();
-        let user_units: Vec<Arc<dyn SkimItem>> = smol::block_on(async {
-            let conn = Connection::session()
-                .await
-                .expect("Failed to connect to system bus");
-            let manager = zbus_systemd::systemd1::ManagerProxy::new(&conn)
-                .await
-                .unwrap();
-            manager.list_units().await.clone()
-        })
-        .expect("Failed to list systemd units")
-        .into_iter()
-        .filter_map(
-            |(
+            )| Unit {
                 name,
                 desc,
                 load_state,
@@ -149,27 +563,280 @@ impl SkimRun for SystemdServices {
                 job_id,
                 job_type,
                 job_object_path,
-            )| {
-                if name.ends_with(".service") {
-                    Some(Arc::new(SystemdService {
-                        name,
-                        desc,
-                        load_state,
-                        is_user: true,
-                        active_state,
-                        sub_state,
-                        follower,
-                        object_path,
-                        job_id,
-                        job_type,
-                        job_object_path,
-                    }) as Arc<dyn SkimItem>)
-                } else {
-                    None
-                }
             },
         )
+        .collect())
+}
+
+/// Runs `action` on `unit` and returns its outcome, once its job finished
+async fn act(bus: Bus, unit: &str, action: UnitAction) -> Result<String> {
+    let manager = bus.manager().await?;
+    let (unit, files) = (unit.to_string(), vec![unit.to_string()]);
+    // Jobs replace conflicting ones, like systemctl does
+    let mode = || String::from("replace");
+    let changes: Vec<(String, String, String)> = match action {
+        UnitAction::ResetFailed => {
+            call::<_, ()>(&manager, action.method(), &(unit,)).await?;
+            return Ok(format!("{} done", action.name()));
+        }
+        UnitAction::Enable => {
+            call::<_, (bool, _)>(&manager, action.method(), &(files, false, false))
+                .await?
+                .1
+        }
+        UnitAction::Disable | UnitAction::Unmask => {
+            call(&manager, action.method(), &(files, false)).await?
+        }
+        UnitAction::Mask => call(&manager, action.method(), &(files, false, false)).await?,
+        UnitAction::Start | UnitAction::Stop | UnitAction::Restart | UnitAction::Reload => {
+            // Subscribed before the job starts, it may be over by the time it is returned
+            manager.subscribe().await?;
+            let mut removed = manager.receive_job_removed().await?;
+            let job: OwnedObjectPath = call(&manager, action.method(), &(unit, mode())).await?;
+            let wait = async {
+                while let Some(signal) = removed.next().await {
+                    let args = signal.args()?;
+                    if args.job == job {
+                        return Ok(args.result);
+                    }
+                }
+                bail!("The bus closed before the job finished")
+            };
+            let timeout = async {
+                smol::Timer::after(JOB_TIMEOUT).await;
+                bail!("Timed out waiting for job {}", job.as_str())
+            };
+            let result = smol::future::or(wait, timeout).await?;
+            if result != "done" {
+                bail!("the job ended with `{result}`, see the logs in the preview");
+            }
+            return Ok(format!("{} done", action.name()));
+        }
+    };
+    // Like `systemctl enable`, so the changes apply to the running manager
+    call::<_, ()>(&manager, "Reload", &()).await?;
+    Ok(if changes.is_empty() {
+        format!("{} done, nothing changed", action.name())
+    } else {
+        format!("{} done", action.name())
+    })
+}
+
+/// Calls a manager method letting polkit ask for authorization, as `systemctl` does:
+/// the system manager denies unprivileged callers otherwise.
+/// The generated `ManagerProxy` methods can not set call flags, hence the raw method names.
+async fn call<B, R>(manager: &ManagerProxy<'_>, method: &str, body: &B) -> Result<R>
+where
+    B: serde::Serialize + DynamicType,
+    R: for<'d> DynamicDeserialize<'d>,
+{
+    manager
+        .inner()
+        .call_with_flags(method, MethodFlags::AllowInteractiveAuth.into(), body)
+        .await?
+        .with_context(|| format!("systemd did not reply to {method}"))
+}
+
+fn results_dir() -> PathBuf {
+    xdg::state_dir().join(RESULTS_DIR)
+}
+
+/// Results by bus and unit, from the `bus@unit` files of `dir`
+fn load_results(dir: &Path) -> HashMap<(String, String), String> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            // Unit names may contain `@` too, bus names do not
+            let (bus, unit) = name.split_once('@').filter(|_| !name.starts_with('.'))?;
+            let message = fs::read_to_string(dir.join(&name)).ok()?;
+            Some(((bus.to_string(), unit.to_string()), message))
+        })
+        .collect()
+}
+
+/// Writes the result of an action, on a single line.
+/// Each unit has its own file, replaced atomically, so concurrent actions do not lose
+/// each other's result and the picker never reads a partial one.
+fn save_result(dir: &Path, bus: Bus, unit: &str, message: &str) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let name = format!("{}@{unit}", bus.name());
+    let tmp = dir.join(format!(".{name}.{}.tmp", std::process::id()));
+    fs::write(&tmp, message.replace(['\n', '\t'], " "))
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, dir.join(&name))
+        .with_context(|| format!("Failed to replace the result of {unit}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+    use skim::field::get_string_by_range;
+
+    use super::*;
+
+    fn state(name: &str, result: Option<&str>) -> UnitState {
+        let path = || OwnedObjectPath::try_from("/").unwrap();
+        UnitState {
+            unit: Unit {
+                name: name.to_string(),
+                desc: String::new(),
+                load_state: String::from("loaded"),
+                active_state: String::from("active"),
+                sub_state: String::from("running"),
+                follower: String::new(),
+                object_path: path(),
+                job_id: 0,
+                job_type: String::new(),
+                job_object_path: path(),
+            },
+            listed: true,
+            result: result.map(String::from),
+        }
+    }
+
+    #[test]
+    fn fields_of_the_line() {
+        let delimiter = Regex::new(DELIMITER).unwrap();
+        for (bus, name) in [
+            (Bus::System, "sshd.service"),
+            (Bus::User, "getty@tty1.service"),
+            (Bus::System, r"dev-disk-by\x2dlabel-data.swap"),
+            (Bus::User, "unknown-suffix.foo"),
+        ] {
+            let line = state(name, Some("restart failed: [denied]")).line(bus);
+            assert_eq!(
+                get_string_by_range(&delimiter, &line, "2"),
+                Some(bus.name())
+            );
+            assert_eq!(get_string_by_range(&delimiter, &line, "4"), Some(name));
+        }
+        assert_eq!(
+            state("sshd.service", None).line(Bus::System),
+            "[system] [service] sshd.service loaded active"
+        );
+    }
+
+    #[test]
+    fn action_methods() {
+        let methods: Vec<_> = [
+            UnitAction::Start,
+            UnitAction::Stop,
+            UnitAction::Restart,
+            UnitAction::Reload,
+            UnitAction::Enable,
+            UnitAction::Disable,
+            UnitAction::Mask,
+            UnitAction::Unmask,
+            UnitAction::ResetFailed,
+        ]
+        .into_iter()
+        .map(|a| (a.name(), a.method()))
         .collect();
-        [system_units, user_units].into_iter().flatten().collect()
+        assert_eq!(
+            methods,
+            [
+                ("start", "StartUnit"),
+                ("stop", "StopUnit"),
+                ("restart", "RestartUnit"),
+                ("reload", "ReloadUnit"),
+                ("enable", "EnableUnitFiles"),
+                ("disable", "DisableUnitFiles"),
+                ("mask", "MaskUnitFiles"),
+                ("unmask", "UnmaskUnitFiles"),
+                ("reset-failed", "ResetFailedUnit"),
+            ]
+        );
+    }
+
+    #[test]
+    fn results_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join(RESULTS_DIR);
+        assert!(load_results(&dir).is_empty());
+        save_result(&dir, Bus::System, "sshd.service", "restart done").unwrap();
+        save_result(
+            &dir,
+            Bus::User,
+            "getty@tty1.service",
+            "stop failed:\n\tdenied",
+        )
+        .unwrap();
+        save_result(&dir, Bus::System, "sshd.service", "stop done").unwrap();
+        let key = |bus: &str, unit: &str| (bus.to_string(), unit.to_string());
+        let results = load_results(&dir);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[&key("system", "sshd.service")], "stop done");
+        // Messages stay on the unit's line
+        assert_eq!(
+            results[&key("user", "getty@tty1.service")],
+            "stop failed:  denied"
+        );
+        // Temporary files of a write in progress are skipped
+        fs::write(dir.join(".system@a.service.1.tmp"), "partial").unwrap();
+        assert_eq!(load_results(&dir).len(), 2);
+    }
+
+    /// 2026-10-19 09:30:00 UTC, 3 hours after boot
+    fn clocks() -> Clocks {
+        Clocks {
+            realtime: 1_792_402_200_000_000,
+            monotonic: 3 * 3_600_000_000,
+        }
+    }
+
+    #[test]
+    fn unit_types() {
+        assert_eq!(UnitType::of("sshd.service"), Some(UnitType::Service));
+        assert_eq!(UnitType::of("getty@tty1.service"), Some(UnitType::Service));
+        assert_eq!(
+            UnitType::of("proc-sys-fs-binfmt_misc.automount"),
+            Some(UnitType::Automount)
+        );
+        // Escaped names keep their suffix after the last dot
+        assert_eq!(
+            UnitType::of(r"dev-disk-by\x2dlabel-data.swap"),
+            Some(UnitType::Swap)
+        );
+        assert_eq!(
+            UnitType::of(r"run-user-1000\x2eold.mount"),
+            Some(UnitType::Mount)
+        );
+        assert_eq!(UnitType::of(r"foo\x2eservice"), None);
+        assert_eq!(UnitType::of("backup.timer.d"), None);
+        assert_eq!(UnitType::of("unknown.foo"), None);
+        assert_eq!(UnitType::of("Service"), None);
+        assert_eq!(UnitType::of("sshd.Service"), None);
+    }
+
+    #[test]
+    fn relative_times() {
+        let now = clocks().realtime;
+        let minute = 60_000_000;
+        assert_eq!(relative_time(0, clocks()), "n/a");
+        assert_eq!(relative_time(now, clocks()), "in 0s");
+        assert_eq!(relative_time(now + 45_000_000, clocks()), "in 45s");
+        assert_eq!(relative_time(now + 125 * minute, clocks()), "in 2h 5m");
+        assert_eq!(relative_time(now - 90 * minute, clocks()), "1h 30m ago");
+        assert_eq!(
+            relative_time(now - (73 * 60 + 20) * minute, clocks()),
+            "3d 1h ago"
+        );
+        assert_eq!(relative_time(now - 30 * minute - 1, clocks()), "30m ago");
+    }
+
+    #[test]
+    fn monotonic_timestamps() {
+        assert_eq!(monotonic_to_realtime(0, clocks()), 0);
+        // 10 minutes after boot is 2h 50m ago, 4 hours after boot is in 1 hour
+        let hour = 3_600_000_000;
+        let realtime = monotonic_to_realtime(hour / 6, clocks());
+        assert_eq!(realtime, clocks().realtime - 17 * hour / 6);
+        assert_eq!(relative_time(realtime, clocks()), "2h 50m ago");
+        assert_eq!(
+            relative_time(monotonic_to_realtime(4 * hour, clocks()), clocks()),
+            "in 1h 0m"
+        );
     }
 }
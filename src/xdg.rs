@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+static APP_DIR: &str = "skim-run";
+
+/// Resolves an XDG base directory from `var`, falling back to `$HOME/<fallback>`.
+/// The returned path points to the skim-run subdirectory and is not created.
+fn base_dir(var: &str, fallback: &str) -> PathBuf {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(fallback)))
+        .unwrap_or_else(std::env::temp_dir)
+        .join(APP_DIR)
+}
+
+/// `$XDG_CACHE_HOME/skim-run`
+#[must_use]
+pub fn cache_dir() -> PathBuf {
+    base_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// `$XDG_STATE_HOME/skim-run`
+#[must_use]
+pub fn state_dir() -> PathBuf {
+    base_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// `$XDG_CONFIG_HOME/skim-run`
+#[must_use]
+pub fn config_dir() -> PathBuf {
+    base_dir("XDG_CONFIG_HOME", ".config")
+}